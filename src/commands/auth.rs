@@ -1,82 +1,380 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::IsTerminal, time::Duration};
 
-use oauth2::PkceCodeChallenge;
+use chrono::{DateTime, Local};
+use clap::{Subcommand, ValueEnum};
+use cliclack::{Input, Password, Select};
+use comfy_table::Cell;
+use oauth2::{CsrfToken, PkceCodeChallenge};
+use reqwest::Url;
+use serde_json::json;
 use supabase_auth::models::{LoginWithOAuthOptions, Provider, Session};
 
-use crate::services::{AuthService, UtilsService};
+use crate::models::auth::{LoginProvider, RefreshStatus, SessionStatus};
+use crate::services::{
+    AUTH_CALLBACK_PORT, AuthService, OutputRow, OutputService, PYRITE_TOKEN_ENV, TeamsService,
+    UtilsService,
+};
+use crate::utils::TABLE_DATE_FORMAT;
+use crate::utils::error::PyriteError;
 
-#[derive(Debug, Clone)]
-pub(crate) struct AuthCommands;
+#[derive(Subcommand, Debug, Clone)]
+#[command(about = "Manage authentication", arg_required_else_help = false)]
+pub(crate) enum AuthCommands {
+    #[command(
+        about = "Show the logged in user, exits with 3 when there is no usable session",
+        visible_alias = "whoami"
+    )]
+    Status,
+    #[command(about = "Print the access token, refreshing it if needed")]
+    Token {
+        #[arg(
+            long,
+            help = "Print the auth header sent with API requests, e.g. for grpcurl -H",
+            conflicts_with = "json"
+        )]
+        header: bool,
+        #[arg(long, help = "Print the token, its expiry and the auth header as JSON")]
+        json: bool,
+        #[arg(long, help = "Print the token even when the output is a terminal")]
+        force: bool,
+    },
+}
 
 impl AuthCommands {
-    pub async fn login() -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            AuthCommands::Status => Self::status().await?,
+            AuthCommands::Token {
+                header,
+                json,
+                force,
+            } => Self::token(header, json, force).await?,
+        }
+        Ok(())
+    }
+
+    pub async fn login(
+        provider: Option<LoginProvider>,
+        no_browser: bool,
+        timeout: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(token) = AuthService::get_env_token() {
+            return Self::login_with_token(token).await;
+        }
+
+        // Check if we have a valid session and return it
+        if let Ok(session) = AuthService::get_session().await {
+            cliclack::outro(format!("Logged in as {}", session.user.email))?;
+            return Ok(());
+        }
+
+        let provider = match provider {
+            Some(provider) => provider,
+            None => Self::select_provider()?,
+        };
+
+        let session = match provider.get_oauth_provider() {
+            None if provider == LoginProvider::MagicLink => Self::login_with_code().await?,
+            None => Self::login_with_password().await?,
+            Some(provider) if no_browser => Self::login_without_browser(provider).await?,
+            Some(provider) => {
+                UtilsService::with_progress(
+                    || AuthCommands::process_login(provider, Duration::from_secs(timeout)),
+                    "Logging in...",
+                    "Login successful",
+                    "Failed to login",
+                )
+                .await? // Session
+            }
+        };
+
+        cliclack::outro(format!("Logged in as {}", session.user.email))?;
+
+        Ok(())
+    }
+
+    // Falls back to GitHub when there is no terminal to pick from
+    fn select_provider() -> Result<LoginProvider, Box<dyn std::error::Error>> {
+        if !std::io::stdin().is_terminal() {
+            return Ok(LoginProvider::Github);
+        }
+
+        let items = LoginProvider::value_variants()
+            .iter()
+            .map(|provider| (*provider, provider.to_string(), ""))
+            .collect::<Vec<_>>();
+
+        let provider = Select::new("Log in with")
+            .items(items.as_slice())
+            .interact()?;
+
+        Ok(provider)
+    }
+
+    // The token is only verified, commands read it from the environment themselves
+    async fn login_with_token(token: String) -> Result<(), Box<dyn std::error::Error>> {
         let session = UtilsService::with_progress(
-            AuthCommands::process_login,
+            || AuthService::get_token_session(&token),
+            "Verifying token...",
+            "Token verified",
+            "Failed to verify token",
+        )
+        .await?;
+
+        cliclack::outro(format!(
+            "Authenticated as {} with {}, commands use it instead of a session",
+            session.user.email, PYRITE_TOKEN_ENV
+        ))?;
+
+        Ok(())
+    }
+
+    async fn login_with_password() -> Result<Session, Box<dyn std::error::Error>> {
+        let email: String = Input::new("Email").interact()?;
+        let password = Password::new("Password").mask('▪').interact()?;
+
+        UtilsService::with_progress(
+            || AuthService::login_with_password(email.trim(), &password),
             "Logging in...",
             "Login successful",
             "Failed to login",
         )
-        .await?; // Session
+        .await
+    }
 
-        cliclack::outro(format!("Logged in as {}", session.user.email))?;
+    async fn login_with_code() -> Result<Session, Box<dyn std::error::Error>> {
+        let email: String = Input::new("Email").interact()?;
+        let email = email.trim();
 
-        Ok(())
+        UtilsService::with_progress(
+            || AuthService::send_login_code(email),
+            "Sending login email...",
+            "Login email sent",
+            "Failed to send login email",
+        )
+        .await?;
+
+        let code: String =
+            Input::new(format!("Enter the code from the email sent to {}", email)).interact()?;
+
+        UtilsService::with_progress(
+            || AuthService::verify_login_code(email, code.trim()),
+            "Logging in...",
+            "Login successful",
+            "Failed to login",
+        )
+        .await
     }
 
-    async fn process_login() -> Result<Session, Box<dyn std::error::Error>> {
-        // Check if we have a valid session and return it
-        {
-            let session = AuthService::get_session().await;
-            if let Ok(session) = session {
-                return Ok(session);
-            }
+    async fn process_login(
+        provider: Provider,
+        timeout: Duration,
+    ) -> Result<Session, Box<dyn std::error::Error>> {
+        // The server is started first, the redirect URL needs its port
+        let listener = AuthService::bind_auth_server().await?;
+        let port = listener.local_addr()?.port();
+
+        let csrf_state = CsrfToken::new_random().into_secret();
+        let (url, pkce_verifier) =
+            Self::get_oauth_url(provider, &AuthService::get_callback_url(port, &csrf_state))?;
+
+        println!("{}", url);
+
+        let session =
+            AuthService::start_auth_server(listener, pkce_verifier, csrf_state, timeout).await?;
+
+        Ok(session)
+    }
+
+    // The browser doesn't need to reach the callback server, the code is read from the
+    // redirect URL pasted by the user
+    async fn login_without_browser(
+        provider: Provider,
+    ) -> Result<Session, Box<dyn std::error::Error>> {
+        let csrf_state = CsrfToken::new_random().into_secret();
+        let callback_url = AuthService::get_callback_url(AUTH_CALLBACK_PORT, &csrf_state);
+        let (url, pkce_verifier) = Self::get_oauth_url(provider, &callback_url)?;
+
+        cliclack::log::step("Open this URL in a browser and log in")?;
+        println!("{}", url);
+
+        let input: String = Input::new("Paste the URL you were redirected to, or the code from it")
+            .placeholder(&format!("{}&code=...", callback_url))
+            .interact()?;
+        let code = Self::get_code_from_input(&input, &csrf_state)?;
+
+        UtilsService::with_progress(
+            || AuthService::exchange_code_for_session(&code, &pkce_verifier),
+            "Logging in...",
+            "Login successful",
+            "Failed to login",
+        )
+        .await
+    }
+
+    // Returns the OAuth URL and the PKCE verifier needed to exchange the code
+    fn get_oauth_url(
+        provider: Provider,
+        redirect_url: &str,
+    ) -> Result<(String, String), Box<dyn std::error::Error>> {
+        let auth_client = AuthService::get_auth_client();
+
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+
+        let options = LoginWithOAuthOptions {
+            query_params: Some(HashMap::from([
+                ("skip_browser_redirect".to_owned(), "true".to_owned()),
+                ("redirect_to".to_owned(), redirect_url.to_owned()),
+                ("response_type".to_owned(), "code".to_owned()),
+                (
+                    "code_challenge".to_owned(),
+                    pkce_challenge.as_str().to_owned(),
+                ),
+                ("code_challenge_method".to_owned(), "S256".to_owned()),
+            ])),
+            ..Default::default()
+        };
+
+        let oauth_res = auth_client.login_with_oauth(provider, Some(options))?;
+
+        Ok((oauth_res.url.to_string(), pkce_verifier.into_secret()))
+    }
+
+    fn get_code_from_input(
+        input: &str,
+        csrf_state: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let input = input.trim();
+
+        let Ok(url) = Url::parse(input) else {
+            // Not a URL, assume the code itself was pasted
+            return Ok(input.to_owned());
+        };
+
+        let query = url.query_pairs().collect::<HashMap<_, _>>();
+        if let Some(err) = query.get("error_description").or(query.get("error")) {
+            return Err(PyriteError::Auth(format!("Failed to login: {}", err)).into());
+        }
+
+        if query.get("state").map(|state| state.as_ref()) != Some(csrf_state) {
+            return Err(PyriteError::Auth(
+                "Invalid state parameter, copy the URL from this login attempt".to_owned(),
+            )
+            .into());
         }
 
-        // If no valid session, proceed with OAuth
-        {
-            let auth_client = AuthService::get_auth_client();
+        match query.get("code") {
+            Some(code) => Ok(code.to_string()),
+            None => Err(PyriteError::Auth(
+                "The URL doesn't contain a code, copy the full URL after logging in".to_owned(),
+            )
+            .into()),
+        }
+    }
+
+    pub async fn status() -> Result<(), Box<dyn std::error::Error>> {
+        let (session, refresh_status) = UtilsService::with_progress(
+            AuthCommands::get_session_status,
+            "Checking session...",
+            "Session checked",
+            "Failed to check session",
+        )
+        .await?;
+
+        let teams = match TeamsService::list_teams().await {
+            Ok(teams_res) => Some(teams_res.teams.into_iter().map(|team| team.name).collect()),
+            Err(err) => {
+                cliclack::log::warning(format!("Failed to list teams: {}", err))?;
+                None
+            }
+        };
 
-            // let session = auth_client
-            // .login_with_email("user@pyrite.cloud", "Pyrite@Cloud")
-            // .await?;
+        let profile = if AuthService::get_env_token().is_some() {
+            PYRITE_TOKEN_ENV.to_owned()
+        } else {
+            AuthService::get_profile().to_owned()
+        };
 
-            let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        OutputService::print_item(&SessionStatus {
+            profile,
+            email: session.user.email,
+            user_id: session.user.id.to_string(),
+            expires_at: DateTime::from_timestamp(session.expires_at as i64, 0)
+                .map(|expires_at| expires_at.to_rfc3339()),
+            refresh_status,
+            teams,
+        })?;
 
-            let options = LoginWithOAuthOptions {
-                query_params: Some(HashMap::from([
-                    ("skip_browser_redirect".to_owned(), "true".to_owned()),
-                    (
-                        "redirect_to".to_owned(),
-                        "http://127.0.0.1:3456/auth/callback".to_owned(),
-                    ),
-                    ("response_type".to_owned(), "code".to_owned()),
-                    (
-                        "code_challenge".to_owned(),
-                        pkce_challenge.as_str().to_owned(),
-                    ),
-                    ("code_challenge_method".to_owned(), "S256".to_owned()),
-                ])),
-                ..Default::default()
-            };
+        Ok(())
+    }
 
-            let oauth_res = auth_client
-                // .login_with_email("user@pyrite.cloud", "Pyrite.Cloud")
-                .login_with_oauth(Provider::Github, Some(options))?;
+    // Refreshes the stored session like any other command, so a session that can't be
+    // refreshed is reported as unusable
+    async fn get_session_status() -> Result<(Session, RefreshStatus), Box<dyn std::error::Error>> {
+        if let Some(token) = AuthService::get_env_token() {
+            let session = AuthService::get_token_session(&token).await?;
+            return Ok((session, RefreshStatus::NotAvailable));
+        }
 
-            println!("{}", oauth_res.url);
+        let Some(stored_session) = AuthService::read_session().await? else {
+            return Err(PyriteError::Auth("Not logged in".to_owned()).into());
+        };
 
-            AuthService::start_auth_server(pkce_verifier.into_secret()).await?;
+        let session =
+            AuthService::get_session()
+                .await
+                .map_err(|err| match PyriteError::from(err) {
+                    err @ PyriteError::Auth(_) => err,
+                    err => PyriteError::Auth(format!("Failed to refresh the session: {}", err)),
+                })?;
+
+        let refresh_status = if session.access_token == stored_session.access_token {
+            RefreshStatus::NotNeeded
+        } else {
+            RefreshStatus::Refreshed
+        };
+        Ok((session, refresh_status))
+    }
 
-            // let session = auth_client.exchange_token_for_session("").await?;
-            // AuthService::write_session(&session)?;
+    pub async fn token(
+        header: bool,
+        json: bool,
+        force: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Keep tokens out of terminal scrollback and logs
+        if std::io::stdout().is_terminal() && !force {
+            return Err(
+                "Refusing to print the token to a terminal, pipe the output or pass --force".into(),
+            );
+        }
 
-            let session = AuthService::get_session().await?;
+        let session = AuthService::get_active_session().await?;
+        let (header_name, header_value) = AuthService::get_auth_header(&session)?;
 
-            Ok(session)
+        if json {
+            let value = json!({
+                "access_token": session.access_token,
+                "expires_at": session.expires_at,
+                "header": {
+                    "name": header_name,
+                    "value": header_value,
+                },
+            });
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        } else if header {
+            println!("{}: {}", header_name, header_value);
+        } else {
+            println!("{}", session.access_token);
         }
+
+        Ok(())
     }
 
-    pub async fn logout() -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn logout(all: bool, all_profiles: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if all_profiles {
+            return Self::logout_all_profiles(all).await;
+        }
+
         let session = UtilsService::with_progress(
             AuthService::read_session,
             "Reading session",
@@ -85,7 +383,8 @@ impl AuthCommands {
         )
         .await?;
 
-        if session.is_some() {
+        if let Some(session) = session {
+            Self::revoke_session(&session, all).await?;
             AuthService::delete_session()?;
             cliclack::outro("Logged out")?;
         } else {
@@ -94,4 +393,90 @@ impl AuthCommands {
 
         Ok(())
     }
+
+    async fn logout_all_profiles(all: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let profiles = AuthService::list_profiles()?;
+
+        for profile in &profiles {
+            if let Some(session) = AuthService::read_profile_session(profile)? {
+                cliclack::log::step(format!("Profile {}", profile))?;
+                Self::revoke_session(&session, all).await?;
+            }
+            AuthService::delete_profile(profile)?;
+        }
+        AuthService::delete_default_profile()?;
+
+        cliclack::outro(format!("Removed {} profiles", profiles.len()))?;
+
+        Ok(())
+    }
+
+    // The local session is removed even if the server can't be reached
+    async fn revoke_session(
+        session: &Session,
+        all: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let res = UtilsService::with_progress(
+            || AuthService::revoke_session(session, all),
+            if all {
+                "Revoking all sessions..."
+            } else {
+                "Revoking session..."
+            },
+            "Session revoked",
+            "Failed to revoke session",
+        )
+        .await;
+
+        if let Err(err) = res {
+            cliclack::log::warning(format!(
+                "The session could not be revoked on the server: {}",
+                err
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+// A single session, so wide output has the same columns
+impl OutputRow for SessionStatus {
+    fn get_header(_wide: bool) -> Vec<&'static str> {
+        vec![
+            "Profile",
+            "Email",
+            "User Id",
+            "Expires At",
+            "Refresh",
+            "Teams",
+        ]
+    }
+
+    fn get_row(&self, _wide: bool) -> Result<Vec<Cell>, Box<dyn std::error::Error>> {
+        let expires_at = match self.expires_at.as_deref() {
+            Some(expires_at) => {
+                let expires_at = DateTime::parse_from_rfc3339(expires_at)?;
+                format!(
+                    "{}\n{}",
+                    expires_at.with_timezone(&Local).format(TABLE_DATE_FORMAT),
+                    UtilsService::get_relative_time(expires_at.to_utc())
+                )
+            }
+            None => "-".to_owned(),
+        };
+        let teams = self
+            .teams
+            .as_ref()
+            .filter(|teams| !teams.is_empty())
+            .map_or("-".to_owned(), |teams| teams.join("\n"));
+
+        Ok(vec![
+            Cell::new(&self.profile),
+            Cell::new(&self.email).fg(comfy_table::Color::White),
+            Cell::new(&self.user_id),
+            Cell::new(expires_at),
+            Cell::new(self.refresh_status.to_string()).fg(comfy_table::Color::White),
+            Cell::new(teams).fg(comfy_table::Color::White),
+        ])
+    }
 }
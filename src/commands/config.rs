@@ -0,0 +1,65 @@
+use clap::Subcommand;
+
+use crate::services::PyriteTomlService;
+use crate::utils::error::PyriteError;
+
+#[derive(Subcommand, Debug, Clone)]
+#[command(about = "Manage pyrite.toml", arg_required_else_help = false)]
+pub(crate) enum ConfigCommands {
+    #[command(about = "Print pyrite.toml merged with an environment overlay")]
+    Render {
+        #[arg(
+            short,
+            help = "Path to the pyrite.toml file",
+            default_value = "pyrite.toml"
+        )]
+        file: String,
+        #[arg(short, long, help = "Environment overlay to apply")]
+        env: Option<String>,
+    },
+    #[command(about = "Print the JSON Schema of pyrite.toml")]
+    Schema,
+    #[command(about = "Check pyrite.toml for mistakes without contacting the API")]
+    Validate {
+        #[arg(
+            short,
+            help = "Path to the pyrite.toml file",
+            default_value = "pyrite.toml"
+        )]
+        file: String,
+        #[arg(short, long, help = "Environment overlay to apply")]
+        env: Option<String>,
+    },
+}
+
+impl ConfigCommands {
+    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            ConfigCommands::Render { file, env } => {
+                let table = PyriteTomlService::render(&file, env.as_deref())?;
+                print!("{}", toml::to_string_pretty(&table)?);
+            }
+            ConfigCommands::Schema => {
+                let schema = PyriteTomlService::get_json_schema();
+                println!("{}", serde_json::to_string_pretty(&schema)?);
+            }
+            ConfigCommands::Validate { file, env } => {
+                let issues = PyriteTomlService::validate(&file, env.as_deref())?;
+                if !issues.is_empty() {
+                    for issue in &issues {
+                        cliclack::log::error(issue)?;
+                    }
+                    return Err(PyriteError::ConfigParse(format!(
+                        "Found {} problems in {}",
+                        issues.len(),
+                        file
+                    ))
+                    .into());
+                }
+
+                cliclack::outro(format!("{} is valid", file))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,69 +1,390 @@
-use std::path::Path;
+use std::time::{Duration, Instant};
 
 use base64::{Engine, prelude::BASE64_STANDARD_NO_PAD};
-use comfy_table::{Table, modifiers, presets};
+use cliclack::spinner;
+use comfy_table::{Cell, Table, modifiers, presets};
 use pyrite_client_rs::pyrite::v1::services::v1::{
-    UpsertServiceDto,
-    common::v1::{Service, ServiceEnvironment},
+    UpsertServiceDto, UpsertServiceResponseDto,
+    common::v1::ServiceEnvironment,
     deployments::v1::{
         DeploymentFileList, DeploymentHealthCheckList, DeploymentPortList, DeploymentRegionList,
-        DeploymentVolumeList, DockerDeploymentDto,
+        DeploymentVolumeList, DockerDeploymentDto, PostgresDeploymentDto,
     },
     upsert_service_dto::DeploymentConfig,
 };
 
 use crate::{
-    models::pyrite_toml::{PyriteToml, TomlService},
-    services::{ServicesService, UtilsService},
+    models::{
+        plan::{PlanAction, ServicePlan},
+        pyrite_toml::{ServiceType, TomlService},
+    },
+    services::{
+        PlanService, PyriteTomlService, ServicesService, UtilsService,
+        service_environments::ServiceEnvironmentsService,
+    },
+    utils::error::PyriteError,
 };
 
+const DEPLOYMENT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+type DeployResult<'a> = (
+    &'a TomlService,
+    Result<UpsertServiceResponseDto, Box<dyn std::error::Error>>,
+);
+
+// A deployment followed by `--wait`, at `idx` in the deploy results
+struct PendingDeployment {
+    idx: usize,
+    service_environment_id: String,
+    // Known when the upsert already returned the new deployment
+    deployment_id: Option<String>,
+    previous_deployment_id: Option<String>,
+    label: String,
+}
+
+impl PendingDeployment {
+    fn new(
+        idx: usize,
+        res: &UpsertServiceResponseDto,
+        previous_deployment_id: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let service_environment = res
+            .service_environment
+            .as_ref()
+            .ok_or("No environment returned by the deployment")?;
+
+        // The upsert may already return the new deployment, otherwise any other than the
+        // previous one is new
+        let deployment_id = UtilsService::get_active_deployment_id(service_environment)
+            .filter(|deployment_id| Some(*deployment_id) != previous_deployment_id.as_deref())
+            .map(str::to_owned);
+
+        Ok(Self {
+            idx,
+            service_environment_id: service_environment.id.to_owned(),
+            deployment_id,
+            previous_deployment_id,
+            label: "Pending".to_owned(),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct DeployCommands;
 
 impl DeployCommands {
-    pub async fn run(file: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn run(
+        file: Option<String>,
+        env: Option<String>,
+        plan: bool,
+        wait: bool,
+        timeout: u64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let file_path = file.unwrap();
+        let pyrite_json = PyriteTomlService::load(&file_path, env.as_deref())?;
+
+        if pyrite_json.services.is_empty() {
+            return Err(
+                PyriteError::ConfigParse(format!("No services found in {}", file_path)).into(),
+            );
+        }
 
-        if !Path::new(&file_path).exists() {
-            return Err(format!("File {} does not exist", file_path).into());
+        // Nothing is deployed if a service is invalid
+        let upsert_service_dtos = pyrite_json
+            .services
+            .iter()
+            .map(|service| {
+                Self::get_upsert_service_dto_from_service(
+                    pyrite_json.project_id.to_owned(),
+                    service,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if plan {
+            return Self::plan(&pyrite_json.project_id, &upsert_service_dtos).await;
         }
 
-        let file_data = std::fs::read_to_string(file_path)?;
-        let pyrite_json: PyriteToml = toml::from_str(&file_data)?;
+        // Waiting needs the deployments that were active before, their status is stale
+        let live_services = if wait {
+            ServicesService::list_services(None, Some(pyrite_json.project_id.to_owned()))
+                .await?
+                .services
+        } else {
+            Vec::new()
+        };
+
+        // Every service is deployed before waiting, so they share one deadline
+        let mut results = Vec::with_capacity(pyrite_json.services.len());
+        let mut pending = Vec::new();
+        for (service, upsert_service_dto) in pyrite_json.services.iter().zip(upsert_service_dtos) {
+            let previous_deployment_id = if wait {
+                match PlanService::get_live_service_environment(
+                    &live_services,
+                    &service.name,
+                    &service.environment,
+                )
+                .await
+                {
+                    Ok(service_environment) => service_environment
+                        .as_ref()
+                        .and_then(UtilsService::get_active_deployment_id)
+                        .map(str::to_owned),
+                    Err(err) => {
+                        results.push((service, Err(err)));
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
 
-        let service = pyrite_json.services.first().unwrap();
-        Self::deploy_service(&pyrite_json.project_id, service).await?;
+            let idx = results.len();
+            let res = Self::deploy_service(service, upsert_service_dto)
+                .await
+                .and_then(|res| {
+                    if wait {
+                        pending.push(PendingDeployment::new(idx, &res, previous_deployment_id)?);
+                    }
+                    Ok(res)
+                });
+            results.push((service, res));
+        }
+
+        if !pending.is_empty() {
+            Self::wait_for_deployments(&mut results, pending, Duration::from_secs(timeout)).await;
+        }
+
+        let table = Self::get_deploy_results_table(&results);
+        println!("{table}");
+
+        let failed = results
+            .iter()
+            .filter(|(_, res)| match res {
+                Ok(res) => Self::get_deployment_status(res)
+                    .is_some_and(UtilsService::is_deployment_status_failed),
+                Err(_) => true,
+            })
+            .count();
+        if failed > 0 {
+            return Err(PyriteError::DeploymentFailed(format!(
+                "{failed} of {} services failed to deploy",
+                results.len()
+            ))
+            .into());
+        }
 
         Ok(())
     }
 
-    async fn deploy_service(
+    async fn plan(
         project_id: &str,
-        service: &TomlService,
+        upsert_service_dtos: &[UpsertServiceDto],
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let upsert_service_dto =
-            Self::get_upsert_service_dto_from_service(project_id.to_string(), service);
+        let plans = UtilsService::with_progress(
+            || async {
+                let services = ServicesService::list_services(None, Some(project_id.to_owned()))
+                    .await?
+                    .services;
 
-        let res = UtilsService::with_progress(
+                let mut plans = Vec::with_capacity(upsert_service_dtos.len());
+                for upsert_service_dto in upsert_service_dtos {
+                    plans.push(PlanService::get_service_plan(upsert_service_dto, &services).await?);
+                }
+
+                Ok(plans)
+            },
+            "Computing plan",
+            "Plan computed",
+            "Failed to compute plan",
+        )
+        .await?;
+
+        let table = Self::get_plan_table(&plans);
+        println!("{table}");
+
+        let changes = plans
+            .iter()
+            .filter(|plan| plan.action != PlanAction::NoOp)
+            .count();
+
+        if changes == 0 {
+            cliclack::outro("No changes, everything is up to date")?;
+            return Ok(());
+        }
+
+        let message = format!("{changes} of {} services will be changed", plans.len());
+        cliclack::outro(&message)?;
+        Err(PyriteError::ChangesPending(message).into())
+    }
+
+    fn get_plan_table(plans: &[ServicePlan]) -> Table {
+        let mut table = Table::new();
+        table
+            .load_preset(presets::UTF8_FULL)
+            .apply_modifier(modifiers::UTF8_ROUND_CORNERS)
+            .set_header(vec![
+                "Service Name",
+                "Environment Name",
+                "Action",
+                "Field",
+                "Current",
+                "Desired",
+            ]);
+
+        for plan in plans {
+            let action = match plan.action {
+                PlanAction::Create => Cell::new("Create").fg(comfy_table::Color::Green),
+                PlanAction::Update => Cell::new("Update").fg(comfy_table::Color::Yellow),
+                PlanAction::NoOp => Cell::new("No-op").fg(comfy_table::Color::Grey),
+            };
+
+            if plan.changes.is_empty() {
+                table.add_row(vec![
+                    Cell::new(&plan.name).fg(comfy_table::Color::White),
+                    Cell::new(&plan.environment),
+                    action,
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(""),
+                ]);
+                continue;
+            }
+
+            for change in &plan.changes {
+                table.add_row(vec![
+                    Cell::new(&plan.name).fg(comfy_table::Color::White),
+                    Cell::new(&plan.environment),
+                    action.clone(),
+                    Cell::new(&change.field),
+                    Cell::new(&change.current).fg(comfy_table::Color::Red),
+                    Cell::new(&change.desired).fg(comfy_table::Color::Green),
+                ]);
+            }
+        }
+
+        table
+    }
+
+    async fn deploy_service(
+        service: &TomlService,
+        upsert_service_dto: UpsertServiceDto,
+    ) -> Result<UpsertServiceResponseDto, Box<dyn std::error::Error>> {
+        UtilsService::with_progress(
             || async { ServicesService::upsert_service(upsert_service_dto).await },
             &format!("Deploying {}", service.name),
             &format!("Deployment of {} successful", service.name),
             &format!("Deployment of {} failed", service.name),
         )
+        .await
+    }
+
+    // Polls every deployment until it reaches a final status or the deadline passes, and
+    // records the outcome in `results`
+    async fn wait_for_deployments(
+        results: &mut [DeployResult<'_>],
+        mut pending: Vec<PendingDeployment>,
+        timeout: Duration,
+    ) {
+        let progress = spinner();
+        progress.start(format!("Waiting for {} deployments", pending.len()));
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut waiting = Vec::with_capacity(pending.len());
+            for mut deployment in pending {
+                let (service, res) = &mut results[deployment.idx];
+                match Self::get_new_deployment_status(&deployment).await {
+                    Ok((service_environment, Some(deployment_status)))
+                        if UtilsService::is_deployment_status_final(deployment_status) =>
+                    {
+                        if let Ok(res) = res {
+                            res.service_environment = Some(service_environment);
+                        }
+                    }
+                    Ok((_, deployment_status)) => {
+                        deployment.label = deployment_status
+                            .map(UtilsService::get_deployment_status_label)
+                            .unwrap_or("Pending".to_owned());
+                        waiting.push(deployment);
+                    }
+                    Err(err) => {
+                        *res = Err(PyriteError::from(err)
+                            .map_message(|message| {
+                                format!("Failed to get the status of {}: {}", service.name, message)
+                            })
+                            .into())
+                    }
+                }
+            }
+            pending = waiting;
+
+            if pending.is_empty() {
+                progress.stop("Deployments finished");
+                return;
+            }
+
+            if Instant::now() >= deadline {
+                progress.error(format!(
+                    "Timed out waiting for {} deployments",
+                    pending.len()
+                ));
+                for deployment in pending {
+                    let (service, res) = &mut results[deployment.idx];
+                    *res = Err(PyriteError::DeploymentFailed(format!(
+                        "Timed out after {}s waiting for {} ({})",
+                        timeout.as_secs(),
+                        service.name,
+                        deployment.label
+                    ))
+                    .into());
+                }
+                return;
+            }
+
+            let labels = pending
+                .iter()
+                .map(|deployment| {
+                    format!("{}: {}", results[deployment.idx].0.name, deployment.label)
+                })
+                .collect::<Vec<_>>();
+            progress.set_message(format!("Waiting for {}", labels.join(", ")));
+            tokio::time::sleep(DEPLOYMENT_POLL_INTERVAL).await;
+        }
+    }
+
+    // The status is only read once the new deployment is active, the previous one is stale
+    async fn get_new_deployment_status(
+        deployment: &PendingDeployment,
+    ) -> Result<(ServiceEnvironment, Option<i32>), Box<dyn std::error::Error>> {
+        let service_environment = ServiceEnvironmentsService::get_service_environment(
+            deployment.service_environment_id.to_owned(),
+        )
         .await?;
 
-        let table =
-            Self::get_service_details_table(res.service.unwrap(), res.service_environment.unwrap())
-                .await?;
+        let active_deployment_id = UtilsService::get_active_deployment_id(&service_environment);
+        let is_new_deployment = match (&deployment.deployment_id, active_deployment_id) {
+            (Some(deployment_id), Some(active_deployment_id)) => {
+                deployment_id == active_deployment_id
+            }
+            (None, Some(active_deployment_id)) => {
+                Some(active_deployment_id) != deployment.previous_deployment_id.as_deref()
+            }
+            (_, None) => false,
+        };
 
-        println!("{table}");
-        Ok(())
+        let deployment_status = UtilsService::get_active_deployment_status(&service_environment)
+            .filter(|_| is_new_deployment);
+        Ok((service_environment, deployment_status))
+    }
+
+    fn get_deployment_status(res: &UpsertServiceResponseDto) -> Option<i32> {
+        res.service_environment
+            .as_ref()
+            .and_then(UtilsService::get_active_deployment_status)
     }
 
-    async fn get_service_details_table(
-        service: Service,
-        service_environment: ServiceEnvironment,
-    ) -> Result<Table, Box<dyn std::error::Error>> {
+    fn get_deploy_results_table(results: &[DeployResult]) -> Table {
         let mut table = Table::new();
         table
             .load_preset(presets::UTF8_FULL)
@@ -74,75 +395,154 @@ impl DeployCommands {
                 "Project Name",
                 "Service Name",
                 "Environment Name",
+                "Deployment Status",
+                "Result",
             ]);
 
-        table.add_row(vec![
-            service.id,
-            service
-                .meta
-                .as_ref()
-                .and_then(|meta| meta.team.as_ref())
-                .map(|team| team.name.to_owned())
-                .unwrap_or_default(),
-            service
-                .meta
-                .as_ref()
-                .and_then(|meta| meta.project.as_ref())
-                .map(|project| project.name.to_owned())
-                .unwrap_or_default(),
-            service.name,
-            service_environment.name,
-        ]);
-
-        Ok(table)
+        for (toml_service, res) in results {
+            match res {
+                Ok(res) => {
+                    let deployment_status = Self::get_deployment_status(res);
+                    let service = res.service.to_owned().unwrap_or_default();
+                    let service_environment =
+                        res.service_environment.to_owned().unwrap_or_default();
+
+                    table.add_row(vec![
+                        Cell::new(service.id),
+                        Cell::new(
+                            service
+                                .meta
+                                .as_ref()
+                                .and_then(|meta| meta.team.as_ref())
+                                .map(|team| team.name.to_owned())
+                                .unwrap_or_default(),
+                        ),
+                        Cell::new(
+                            service
+                                .meta
+                                .as_ref()
+                                .and_then(|meta| meta.project.as_ref())
+                                .map(|project| project.name.to_owned())
+                                .unwrap_or_default(),
+                        ),
+                        Cell::new(service.name).fg(comfy_table::Color::White),
+                        Cell::new(service_environment.name),
+                        deployment_status.map_or(Cell::new(""), |deployment_status| {
+                            Cell::new(UtilsService::get_deployment_status_label(deployment_status))
+                                .fg(UtilsService::get_deployment_status_color(deployment_status))
+                        }),
+                        if deployment_status.is_some_and(UtilsService::is_deployment_status_failed)
+                        {
+                            Cell::new("Failed").fg(comfy_table::Color::Red)
+                        } else {
+                            Cell::new("Deployed").fg(comfy_table::Color::Green)
+                        },
+                    ]);
+                }
+                Err(err) => {
+                    table.add_row(vec![
+                        Cell::new(""),
+                        Cell::new(""),
+                        Cell::new(""),
+                        Cell::new(&toml_service.name).fg(comfy_table::Color::White),
+                        Cell::new(&toml_service.environment),
+                        Cell::new(""),
+                        Cell::new(format!("Failed: {err}")).fg(comfy_table::Color::Red),
+                    ]);
+                }
+            }
+        }
+
+        table
     }
 
     fn get_upsert_service_dto_from_service(
         project_id: String,
         service: &TomlService,
-    ) -> UpsertServiceDto {
-        UpsertServiceDto {
+    ) -> Result<UpsertServiceDto, Box<dyn std::error::Error>> {
+        let deployment_config = match Self::get_service_type(service)? {
+            ServiceType::Docker => Self::get_docker_deployment_config(service)?,
+            ServiceType::Postgres => Self::get_postgres_deployment_config(service)?,
+        };
+
+        Ok(UpsertServiceDto {
             name: service.name.to_owned(),
             environment: Some(service.environment.to_owned()),
             r#type: service.r#type.to_owned(),
             project_id,
 
-            deployment_config: Some(DeploymentConfig::DockerConfig(DockerDeploymentDto {
-                image: service.image.to_owned(),
-                registry_id: service.registry_id.to_owned(),
-                command: service.command.to_owned(),
-                args: service.args.to_owned().map(|args| args.join(" ")),
-                runtime: service.runtime.to_owned(),
-                is_private: service.is_private.to_owned(),
-                is_privileged: service.is_privileged.to_owned(),
-                plan: service.plan.to_owned(),
-                with_project_env: service.with_project_env.to_owned(),
-                env: service
-                    .env
-                    .to_owned()
-                    .map(|env| serde_json::to_string(&env).unwrap())
-                    .map(|env_str| BASE64_STANDARD_NO_PAD.encode(env_str)),
-                ports_list: service
-                    .ports
-                    .to_owned()
-                    .map(|ports| DeploymentPortList { ports }),
-                health_checks_list: service
-                    .health_checks
-                    .to_owned()
-                    .map(|health_checks| DeploymentHealthCheckList { health_checks }),
-                regions_list: service
-                    .regions
-                    .to_owned()
-                    .map(|regions| DeploymentRegionList { regions }),
-                volumes_list: service
-                    .volumes
-                    .to_owned()
-                    .map(|volumes| DeploymentVolumeList { volumes }),
-                files_list: service
-                    .files
-                    .to_owned()
-                    .map(|files| DeploymentFileList { files }),
-            })),
-        }
+            deployment_config: Some(deployment_config),
+        })
+    }
+
+    fn get_service_type(service: &TomlService) -> Result<ServiceType, PyriteError> {
+        ServiceType::from_name(&service.r#type)
+            .map_err(|err| PyriteError::Validation(format!("{}: {}", service.name, err)))
+    }
+
+    // Worded like the issues of `pyrite config validate`
+    fn get_required_key(
+        service: &TomlService,
+        key: &str,
+        value: &Option<String>,
+    ) -> Result<String, PyriteError> {
+        value.to_owned().ok_or_else(|| {
+            PyriteError::ConfigParse(format!("{}: missing key {}", service.name, key))
+        })
+    }
+
+    fn get_docker_deployment_config(
+        service: &TomlService,
+    ) -> Result<DeploymentConfig, Box<dyn std::error::Error>> {
+        Ok(DeploymentConfig::DockerConfig(DockerDeploymentDto {
+            image: Self::get_required_key(service, "image", &service.image)?,
+            registry_id: service.registry_id.to_owned(),
+            command: service.command.to_owned(),
+            args: service.args.to_owned().map(|args| args.join(" ")),
+            runtime: Self::get_required_key(service, "runtime", &service.runtime)?,
+            is_private: service.is_private.to_owned(),
+            is_privileged: service.is_privileged.to_owned(),
+            plan: service.plan.to_owned(),
+            with_project_env: service.with_project_env.to_owned(),
+            env: service
+                .env
+                .to_owned()
+                .map(|env| serde_json::to_string(&env).unwrap())
+                .map(|env_str| BASE64_STANDARD_NO_PAD.encode(env_str)),
+            ports_list: service
+                .ports
+                .to_owned()
+                .map(|ports| DeploymentPortList { ports }),
+            health_checks_list: service
+                .health_checks
+                .to_owned()
+                .map(|health_checks| DeploymentHealthCheckList { health_checks }),
+            regions_list: service
+                .regions
+                .to_owned()
+                .map(|regions| DeploymentRegionList { regions }),
+            volumes_list: service
+                .volumes
+                .to_owned()
+                .map(|volumes| DeploymentVolumeList { volumes }),
+            files_list: service
+                .files
+                .to_owned()
+                .map(|files| DeploymentFileList { files }),
+        }))
+    }
+
+    fn get_postgres_deployment_config(
+        service: &TomlService,
+    ) -> Result<DeploymentConfig, Box<dyn std::error::Error>> {
+        Ok(DeploymentConfig::PostgresConfig(PostgresDeploymentDto {
+            version: Self::get_required_key(service, "version", &service.version)?,
+            plan: service.plan.to_owned(),
+            storage: service.storage,
+            regions_list: service
+                .regions
+                .to_owned()
+                .map(|regions| DeploymentRegionList { regions }),
+        }))
     }
 }
@@ -8,9 +8,10 @@ use serde_json::Value;
 
 use crate::models::options::Meta;
 use crate::models::vars::{After, QuestionType, TemplateVars};
+use crate::services::EndpointsService;
 use crate::services::UtilsService;
+use crate::utils::DOCKER_FILE;
 use crate::utils::handlebars::setup_handlebars;
-use crate::utils::{DOCKER_FILE, ERR_MSG, WORKFLOWS_BASE_URL};
 
 #[derive(Subcommand, Debug, Clone)]
 pub(crate) enum DockerCommands {
@@ -38,20 +39,17 @@ impl DockerCommands {
 
                 let ans = Select::new("Which template would you like to use?")
                     .items(options.as_slice())
-                    .interact();
+                    .interact()?;
 
-                if let Ok(choice) = ans {
-                    Self::process_choice(choice.to_owned()).await?;
-                } else {
-                    return Err(ERR_MSG)?;
-                }
+                Self::process_choice(ans.to_owned()).await?;
             }
         }
         Ok(())
     }
 
     async fn fetch_metadata() -> Result<Meta, Box<dyn std::error::Error>> {
-        let meta = reqwest::get(format!("{WORKFLOWS_BASE_URL}{}", "/templates/options.json"))
+        let workflows_url = EndpointsService::get_workflows_url();
+        let meta = reqwest::get(format!("{workflows_url}{}", "/templates/options.json"))
             .await?
             .json::<Meta>()
             .await?;
@@ -60,7 +58,8 @@ impl DockerCommands {
     }
 
     async fn fetch_questions(path: &str) -> Result<TemplateVars, Box<dyn std::error::Error>> {
-        let path = format!("{WORKFLOWS_BASE_URL}{path}{}", "/vars.json");
+        let workflows_url = EndpointsService::get_workflows_url();
+        let path = format!("{workflows_url}{path}{}", "/vars.json");
         let t_vars = reqwest::get(path).await?.json::<TemplateVars>().await?;
         Ok(t_vars)
     }
@@ -120,12 +119,11 @@ impl DockerCommands {
                     }
                     let ans = ans_input.required(true).interact::<String>();
 
-                    if let Ok(choice) = ans {
-                        answers.insert(question.var_name, choice.into());
-                    } else if let Some(choice) = question.default {
-                        answers.insert(question.var_name, choice.into());
-                    } else {
-                        return Err(ERR_MSG)?;
+                    match (ans, question.default) {
+                        (Ok(choice), _) | (Err(_), Some(choice)) => {
+                            answers.insert(question.var_name, choice.into());
+                        }
+                        (Err(err), None) => return Err(err)?,
                     }
                 }
                 QuestionType::Select => {
@@ -138,21 +136,13 @@ impl DockerCommands {
 
                     let ans = Select::new(&question.message)
                         .items(options.as_slice())
-                        .interact();
+                        .interact()?;
 
-                    if let Ok(choice) = ans {
-                        answers.insert(question.var_name, choice.into());
-                    } else {
-                        return Err(ERR_MSG)?;
-                    }
+                    answers.insert(question.var_name, ans.into());
                 }
                 QuestionType::Confirm => {
-                    let ans = Confirm::new(&question.message).interact();
-                    if let Ok(choice) = ans {
-                        answers.insert(question.var_name, choice.into());
-                    } else {
-                        return Err(ERR_MSG)?;
-                    }
+                    let ans = Confirm::new(&question.message).interact()?;
+                    answers.insert(question.var_name, ans.into());
                 }
             }
         }
@@ -164,8 +154,9 @@ impl DockerCommands {
             return Ok((Some(After::new(new_goto)), None));
         }
 
+        let workflows_url = EndpointsService::get_workflows_url();
         let file_path = format!(
-            "{WORKFLOWS_BASE_URL}{path}/{}",
+            "{workflows_url}{path}/{}",
             t_vars.name.unwrap_or(DOCKER_FILE.to_owned())
         );
 
@@ -1,12 +1,12 @@
 use chrono::{DateTime, Local};
 use clap::Subcommand;
 use comfy_table::Cell;
-use comfy_table::Table;
-use comfy_table::modifiers;
-use comfy_table::presets;
-use pyrite_client_rs::pyrite::v1::services::v1::common::v1::ServiceEnvironment;
-use pyrite_client_rs::pyrite::v1::services::v1::common::v1::service_environment;
+use pyrite_client_rs::pyrite::v1::services::v1::common::v1::{
+    ServiceEnvironment, service_environment::ActiveDeployment,
+};
 
+use crate::services::OutputRow;
+use crate::services::OutputService;
 use crate::services::UtilsService;
 use crate::services::service_environments::ServiceEnvironmentsService;
 use crate::utils::TABLE_DATE_FORMAT;
@@ -41,101 +41,106 @@ impl EnvironmentsCommands {
             EnvironmentsCommands::List { service_id } => {
                 let service_environments_res =
                     ServiceEnvironmentsService::list_service_environments(service_id).await?;
-                let service_environments = service_environments_res.service_environments;
-                if service_environments.is_empty() {
-                    cliclack::outro("No service environments found")?;
-                } else {
-                    let table = Self::get_service_environments_table(service_environments)?;
-                    println!("{table}");
-                }
+                OutputService::print_list(
+                    &service_environments_res.service_environments,
+                    "No service environments found",
+                )?;
             }
             EnvironmentsCommands::Get { environment_id } => {
                 let service_environment =
                     ServiceEnvironmentsService::get_service_environment(environment_id).await?;
-                let table = Self::get_service_environments_table(vec![service_environment])?;
-                println!("{table}");
+                OutputService::print_item(&service_environment)?;
             }
         }
         Ok(())
     }
+}
 
-    fn get_service_environments_table(
-        service_environments: Vec<ServiceEnvironment>,
-    ) -> Result<Table, Box<dyn std::error::Error>> {
-        let mut table = Table::new();
-
-        table
-            .load_preset(presets::UTF8_FULL)
-            .apply_modifier(modifiers::UTF8_ROUND_CORNERS)
-            .set_header(vec![
-                "Environment Id",
-                "Environment Name",
-                "Service Name",
-                "Type",
-                "Status",
-                "Deployment Status",
-                "Created At",
-                "Updated At",
-            ]);
-
-        for service_environment in service_environments {
-            let created_at = DateTime::parse_from_rfc3339(service_environment.created_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
+impl OutputRow for ServiceEnvironment {
+    fn get_header(wide: bool) -> Vec<&'static str> {
+        let mut header = vec![
+            "Environment Id",
+            "Environment Name",
+            "Service Name",
+            "Type",
+            "Status",
+            "Deployment Status",
+            "Created At",
+            "Updated At",
+        ];
+        if wide {
+            header.extend(["Service Id", "Image", "Plan", "Regions", "Age"]);
+        }
+        header
+    }
 
-            let updated_at = DateTime::parse_from_rfc3339(service_environment.updated_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
+    fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn std::error::Error>> {
+        let created_at =
+            DateTime::parse_from_rfc3339(self.created_at.as_str())?.with_timezone(&Local);
+        let updated_at =
+            DateTime::parse_from_rfc3339(self.updated_at.as_str())?.with_timezone(&Local);
 
-            let deployment_status =
-                service_environment
-                    .active_deployment
-                    .map(|active_deployment| match active_deployment {
-                        service_environment::ActiveDeployment::DockerDeployment(deployment) => {
-                            deployment.status
-                        }
-                        service_environment::ActiveDeployment::PostgresDeployment(deployment) => {
-                            deployment.status
-                        }
-                    });
+        let service = self.meta.as_ref().and_then(|meta| meta.service.as_ref());
+        let deployment_status = UtilsService::get_active_deployment_status(self);
 
-            table.add_row(vec![
-                Cell::new(service_environment.id),
-                Cell::new(service_environment.name).fg(comfy_table::Color::White),
-                Cell::new(
-                    service_environment
-                        .meta
-                        .as_ref()
-                        .and_then(|meta| meta.service.as_ref())
-                        .map(|service| service.name.to_owned())
-                        .unwrap_or_default(),
-                )
-                .fg(comfy_table::Color::White),
-                Cell::new(
-                    service_environment
-                        .meta
-                        .as_ref()
-                        .and_then(|meta| meta.service.as_ref())
-                        .map(|service| service.r#type.to_uppercase())
-                        .unwrap_or_default(),
+        let mut row = vec![
+            Cell::new(&self.id),
+            Cell::new(&self.name).fg(comfy_table::Color::White),
+            Cell::new(
+                service
+                    .map(|service| service.name.to_owned())
+                    .unwrap_or_default(),
+            )
+            .fg(comfy_table::Color::White),
+            Cell::new(
+                service
+                    .map(|service| service.r#type.to_uppercase())
+                    .unwrap_or_default(),
+            ),
+            Cell::new(UtilsService::get_service_status_label(self.status))
+                .fg(UtilsService::get_service_status_color(self.status)),
+            deployment_status.map_or(Cell::new(""), |deployment_status| {
+                Cell::new(UtilsService::get_deployment_status_label(deployment_status))
+                    .fg(UtilsService::get_deployment_status_color(deployment_status))
+            }),
+            Cell::new(created_at.format(TABLE_DATE_FORMAT)),
+            Cell::new(updated_at.format(TABLE_DATE_FORMAT)),
+        ];
+        if wide {
+            // Postgres has no image, show its version instead
+            let (image, plan, regions_list) = match &self.active_deployment {
+                Some(ActiveDeployment::DockerDeployment(deployment)) => (
+                    deployment.image.to_owned(),
+                    deployment.plan.to_owned(),
+                    deployment.regions_list.as_ref(),
                 ),
-                Cell::new(UtilsService::get_service_status_label(
-                    service_environment.status,
-                ))
-                .fg(UtilsService::get_service_status_color(
-                    service_environment.status,
-                )),
-                deployment_status.map_or(Cell::new(""), |deployment_status| {
-                    Cell::new(UtilsService::get_deployment_status_label(deployment_status))
-                        .fg(UtilsService::get_deployment_status_color(deployment_status))
-                }),
-                Cell::new(created_at),
-                Cell::new(updated_at),
+                Some(ActiveDeployment::PostgresDeployment(deployment)) => (
+                    format!("postgres:{}", deployment.version),
+                    deployment.plan.to_owned(),
+                    deployment.regions_list.as_ref(),
+                ),
+                None => Default::default(),
+            };
+            let regions = regions_list
+                .map(|regions_list| {
+                    regions_list
+                        .regions
+                        .iter()
+                        .map(|region| region.region.to_owned())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+
+            row.extend([
+                Cell::new(&self.service_id),
+                Cell::new(image),
+                Cell::new(plan),
+                Cell::new(regions),
+                Cell::new(UtilsService::get_relative_time(created_at.to_utc())),
             ]);
         }
 
-        Ok(table)
+        Ok(row)
     }
 }
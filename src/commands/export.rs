@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use toml::Table;
+
+use crate::{
+    services::{
+        PyriteTomlService, ServicesService, UtilsService,
+        service_environments::ServiceEnvironmentsService,
+    },
+    utils::error::PyriteError,
+};
+
+#[derive(Debug, Clone)]
+pub(crate) struct ExportCommands;
+
+impl ExportCommands {
+    pub async fn run(
+        project_id: String,
+        file: String,
+        include_secrets: bool,
+        force: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if Path::new(&file).exists() && !force {
+            return Err(PyriteError::Validation(format!(
+                "File {} already exists, use --force to overwrite it",
+                file
+            ))
+            .into());
+        }
+
+        let services = UtilsService::with_progress(
+            || async {
+                let services = ServicesService::list_services(None, Some(project_id.to_owned()))
+                    .await?
+                    .services;
+
+                let mut toml_services = Vec::new();
+                let mut skipped = Vec::new();
+                for service in services {
+                    let service_environments =
+                        ServiceEnvironmentsService::list_service_environments(
+                            service.id.to_owned(),
+                        )
+                        .await?
+                        .service_environments;
+
+                    for service_environment in service_environments {
+                        match PyriteTomlService::export_service_environment(
+                            &service,
+                            &service_environment,
+                            include_secrets,
+                        )? {
+                            Some(toml_service) => {
+                                toml_services.push(toml::Value::Table(toml_service))
+                            }
+                            None => skipped
+                                .push(format!("{} ({})", service.name, service_environment.name)),
+                        }
+                    }
+                }
+
+                Ok((toml_services, skipped))
+            },
+            "Exporting services",
+            "Exported services",
+            "Failed to export services",
+        )
+        .await?;
+
+        let (toml_services, skipped) = services;
+        for skipped in skipped {
+            cliclack::log::warning(format!("Skipped {}, it has no active deployment", skipped))?;
+        }
+
+        if toml_services.is_empty() {
+            return Err(PyriteError::NotFound("No deployed services found".to_owned()).into());
+        }
+
+        let count = toml_services.len();
+        let mut pyrite_toml = Table::new();
+        pyrite_toml.insert("project_id".to_owned(), project_id.into());
+        pyrite_toml.insert("services".to_owned(), toml::Value::Array(toml_services));
+
+        std::fs::write(&file, toml::to_string_pretty(&pyrite_toml)?)?;
+
+        if include_secrets {
+            cliclack::outro(format!("Exported {} services to {}", count, file))?;
+        } else {
+            cliclack::outro(format!(
+                "Exported {} services to {}, env values are read from environment variables on deploy",
+                count, file
+            ))?;
+        }
+
+        Ok(())
+    }
+}
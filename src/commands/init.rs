@@ -0,0 +1,180 @@
+use std::{fs, path::Path};
+
+use cliclack::{Confirm, Input};
+use toml::Table;
+
+use crate::{
+    commands::services::ServicesCommands,
+    services::PyriteTomlService,
+    utils::{DOCKER_FILE, error::PyriteError},
+};
+
+const PYRITE_TOML_FILE: &str = "pyrite.toml";
+
+#[derive(Debug, Clone)]
+pub(crate) struct InitCommands;
+
+impl InitCommands {
+    pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+        cliclack::intro("Create pyrite.toml")?;
+
+        if Path::new(PYRITE_TOML_FILE).exists() {
+            let overwrite = Confirm::new(format!(
+                "{} already exists, overwrite it?",
+                PYRITE_TOML_FILE
+            ))
+            .initial_value(false)
+            .interact()?;
+
+            if !overwrite {
+                cliclack::outro_cancel("Cancelled")?;
+                return Ok(());
+            }
+        }
+
+        let team_id = ServicesCommands::select_team().await?;
+        let project_id = ServicesCommands::select_project(&team_id)
+            .await?
+            .ok_or("Select a project to create pyrite.toml")?;
+
+        let default_name = std::env::current_dir()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let name: String = Input::new("Service name")
+            .default_input(&default_name)
+            .placeholder(&default_name)
+            .required(true)
+            .interact()?;
+
+        let environment: String = Input::new("Environment")
+            .default_input("production")
+            .placeholder("production")
+            .required(true)
+            .interact()?;
+
+        let image: String = Input::new("Docker image")
+            .placeholder("ghcr.io/org/app:latest")
+            .required(true)
+            .interact()?;
+
+        let plan: String = Input::new("Plan")
+            .required(true)
+            .validate(|input: &String| Self::validate_name(input))
+            .interact()?;
+
+        let runtime: String = Input::new("Runtime")
+            .required(true)
+            .validate(|input: &String| Self::validate_name(input))
+            .interact()?;
+
+        // Prefill the ports from a Dockerfile made by `pyrite docker init`
+        let exposed_ports = Self::get_exposed_ports(DOCKER_FILE);
+        if !exposed_ports.is_empty() {
+            cliclack::log::info(format!(
+                "Found EXPOSE {} in {}",
+                exposed_ports.join(" "),
+                DOCKER_FILE
+            ))?;
+        }
+
+        let ports: String = Input::new("Ports (comma separated)")
+            .default_input(&exposed_ports.join(", "))
+            .placeholder("8080")
+            .required(false)
+            .validate(|input: &String| Self::parse_ports(input).map(|_| ()))
+            .interact()?;
+        let ports = Self::parse_ports(&ports)?;
+
+        let mut service = Table::new();
+        service.insert("name".to_owned(), name.into());
+        service.insert("environment".to_owned(), environment.into());
+        service.insert("type".to_owned(), "docker".into());
+        service.insert("image".to_owned(), image.into());
+        service.insert("plan".to_owned(), plan.into());
+        service.insert("runtime".to_owned(), runtime.into());
+        if !ports.is_empty() {
+            let ports = ports
+                .into_iter()
+                .map(|port| {
+                    let mut port_table = Table::new();
+                    port_table.insert("port".to_owned(), toml::Value::Integer(port));
+                    port_table.insert("protocol".to_owned(), "tcp".into());
+                    toml::Value::Table(port_table)
+                })
+                .collect();
+            service.insert("ports".to_owned(), toml::Value::Array(ports));
+        }
+
+        let mut pyrite_toml = Table::new();
+        pyrite_toml.insert("project_id".to_owned(), project_id.into());
+        pyrite_toml.insert(
+            "services".to_owned(),
+            toml::Value::Array(vec![toml::Value::Table(service)]),
+        );
+
+        // Make sure the generated file can be deployed before replacing an existing one
+        let source = toml::to_string_pretty(&pyrite_toml)?;
+        let issues = PyriteTomlService::validate_source(PYRITE_TOML_FILE, &source)?;
+        if !issues.is_empty() {
+            for issue in &issues {
+                cliclack::log::error(issue)?;
+            }
+            return Err(PyriteError::ConfigParse(format!(
+                "Found {} problems in the generated {}, it was not written",
+                issues.len(),
+                PYRITE_TOML_FILE
+            ))
+            .into());
+        }
+
+        fs::write(PYRITE_TOML_FILE, source)?;
+
+        cliclack::outro(format!(
+            "Created {}, run `pyrite deploy --plan` to preview the deployment",
+            PYRITE_TOML_FILE
+        ))?;
+
+        Ok(())
+    }
+
+    // Plans and runtimes are single words, the API checks that they exist on deploy
+    fn validate_name(input: &str) -> Result<(), &'static str> {
+        if input.is_empty() || input.contains(char::is_whitespace) {
+            return Err("Must be a single word without spaces");
+        }
+        Ok(())
+    }
+
+    fn get_exposed_ports(docker_file: &str) -> Vec<String> {
+        let Ok(data) = fs::read_to_string(docker_file) else {
+            return Vec::new();
+        };
+
+        data.lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                let (instruction, args) = line.split_once(char::is_whitespace)?;
+                instruction.eq_ignore_ascii_case("EXPOSE").then_some(args)
+            })
+            .flat_map(|args| args.split_whitespace())
+            // Drop the protocol, e.g. `8080/tcp`
+            .filter_map(|port| port.split('/').next())
+            .filter(|port| port.parse::<u16>().is_ok())
+            .map(str::to_owned)
+            .collect()
+    }
+
+    fn parse_ports(input: &str) -> Result<Vec<i64>, &'static str> {
+        input
+            .split(',')
+            .map(str::trim)
+            .filter(|port| !port.is_empty())
+            .map(|port| match port.parse::<u16>() {
+                Ok(port) if port > 0 => Ok(port as i64),
+                _ => Err("Ports must be numbers between 1 and 65535"),
+            })
+            .collect()
+    }
+}
@@ -1,29 +1,176 @@
 pub mod auth;
+pub mod config;
 pub mod deploy;
 pub mod docker;
 pub mod environments;
+pub mod export;
+pub mod init;
+pub mod profile;
 pub mod projects;
 pub mod services;
 pub mod teams;
 
-use clap::{Parser, Subcommand};
+use auth::AuthCommands;
+use clap::{Args, Parser, Subcommand};
+use config::ConfigCommands;
 use docker::DockerCommands;
 use environments::EnvironmentsCommands;
+use profile::ProfileCommands;
 use projects::ProjectsCommands;
 use services::ServicesCommands;
 use teams::TeamsCommands;
 
+use crate::{
+    models::{auth::LoginProvider, endpoints::EndpointsConfig, output::OutputFormat},
+    utils::error::EXIT_CODES_HELP,
+};
+
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = EXIT_CODES_HELP)]
 pub(crate) struct Cli {
     #[command(subcommand)]
     pub(crate) cmd: Commands,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_PROFILE",
+        help = "Auth profile to use, defaults to the one set by `pyrite profile use`"
+    )]
+    pub(crate) profile: Option<String>,
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_enum,
+        env = "PYRITE_OUTPUT",
+        default_value_t = OutputFormat::Table,
+        help = "Output format of list, get and status commands"
+    )]
+    pub(crate) output: OutputFormat,
+    #[arg(
+        long,
+        global = true,
+        help = "Handlebars template rendered for each item of list, get and status commands, e.g. '{{id}}\\t{{name}}', `\\t` and `\\n` are read as a tab and a newline"
+    )]
+    pub(crate) format: Option<String>,
+    #[command(flatten)]
+    pub(crate) endpoints: EndpointsArgs,
+}
+
+// Overrides `~/.pyrite/config.toml`, e.g. to point at staging or a local stack
+#[derive(Args, Debug, Clone)]
+pub(crate) struct EndpointsArgs {
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_API_URL",
+        help = "gRPC API endpoint",
+        hide_short_help = true
+    )]
+    pub(crate) api_url: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_WORKFLOWS_URL",
+        help = "Base URL of the Dockerfile templates",
+        hide_short_help = true
+    )]
+    pub(crate) workflows_url: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_SUPABASE_URL",
+        help = "Supabase project URL used for auth",
+        hide_short_help = true
+    )]
+    pub(crate) supabase_url: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_SUPABASE_KEY",
+        hide_env_values = true,
+        help = "Supabase API key used for auth",
+        hide_short_help = true
+    )]
+    pub(crate) supabase_key: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_CONNECT_TIMEOUT",
+        help = "Seconds to wait for the API connection",
+        hide_short_help = true
+    )]
+    pub(crate) connect_timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_REQUEST_TIMEOUT",
+        help = "Seconds to wait for each API request",
+        hide_short_help = true
+    )]
+    pub(crate) request_timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        env = "PYRITE_RETRIES",
+        help = "Retries for API requests that fail with a transient error",
+        hide_short_help = true
+    )]
+    pub(crate) retries: Option<u32>,
+}
+
+impl From<EndpointsArgs> for EndpointsConfig {
+    fn from(args: EndpointsArgs) -> Self {
+        Self {
+            api_url: args.api_url,
+            workflows_url: args.workflows_url,
+            supabase_url: args.supabase_url,
+            supabase_key: args.supabase_key,
+            connect_timeout: args.connect_timeout,
+            request_timeout: args.request_timeout,
+            retries: args.retries,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug, Clone)]
 pub(crate) enum Commands {
-    Login,
-    Logout,
+    #[command(
+        after_help = "In CI, set PYRITE_TOKEN to an API token instead of logging in. Every command uses it and no session is saved."
+    )]
+    Login {
+        #[arg(
+            long,
+            value_enum,
+            help = "Log in with this provider instead of picking one"
+        )]
+        provider: Option<LoginProvider>,
+        #[arg(
+            long,
+            help = "Print the login URL and paste the redirect URL back, for remote machines"
+        )]
+        no_browser: bool,
+        #[arg(
+            long,
+            help = "Seconds to wait for the browser login to finish",
+            default_value_t = 300
+        )]
+        timeout: u64,
+    },
+    Logout {
+        #[arg(long, help = "Revoke every session of the user, on all devices")]
+        all: bool,
+        #[arg(long, help = "Log out of every stored profile and remove them")]
+        all_profiles: bool,
+    },
+    #[command(about = "Show the logged in user, exits with 3 when there is no usable session")]
+    Whoami,
+    Auth {
+        #[command(subcommand)]
+        auth_cmd: AuthCommands,
+    },
+    #[command(about = "Create pyrite.toml for the current directory")]
+    Init,
     Docker {
         #[command(subcommand)]
         docker_cmd: DockerCommands,
@@ -44,6 +191,34 @@ pub(crate) enum Commands {
         #[command(subcommand)]
         environments_cmd: EnvironmentsCommands,
     },
+    Profile {
+        #[command(subcommand)]
+        profile_cmd: ProfileCommands,
+    },
+    Config {
+        #[command(subcommand)]
+        config_cmd: ConfigCommands,
+    },
+    #[command(about = "Generate pyrite.toml from the services of a project")]
+    Export {
+        #[arg(
+            short,
+            long = "project",
+            help = "Export services of project id",
+            visible_alias = "project-id"
+        )]
+        project_id: String,
+        #[arg(
+            short,
+            help = "Path to the pyrite.toml file",
+            default_value = "pyrite.toml"
+        )]
+        file: String,
+        #[arg(long, help = "Write env values instead of ${VAR} placeholders")]
+        include_secrets: bool,
+        #[arg(long, help = "Overwrite the file if it exists")]
+        force: bool,
+    },
     Deploy {
         #[arg(
             short,
@@ -51,5 +226,47 @@ pub(crate) enum Commands {
             default_value = Some("pyrite.toml")
         )]
         file: Option<String>,
+        #[arg(short, long, help = "Environment overlay to apply")]
+        env: Option<String>,
+        #[arg(
+            long,
+            help = "Show the changes that would be made without deploying, exits with 2 when there are changes"
+        )]
+        plan: bool,
+        #[arg(
+            long,
+            help = "Wait for the deployments to finish, exits with an error if any of them fail",
+            conflicts_with = "plan"
+        )]
+        wait: bool,
+        #[arg(
+            long,
+            help = "Seconds to wait for the deployments to finish",
+            default_value_t = 600,
+            requires = "wait"
+        )]
+        timeout: u64,
     },
 }
+
+impl Commands {
+    // Whether the command prints through `OutputService`, others ignore `--output` and `--format`
+    pub(crate) fn has_output(&self) -> bool {
+        match self {
+            Commands::Whoami
+            | Commands::Teams { .. }
+            | Commands::Projects { .. }
+            | Commands::Services { .. }
+            | Commands::Environments { .. } => true,
+            Commands::Auth { auth_cmd } => matches!(auth_cmd, AuthCommands::Status),
+            Commands::Profile { profile_cmd } => matches!(profile_cmd, ProfileCommands::List),
+            Commands::Login { .. }
+            | Commands::Logout { .. }
+            | Commands::Init
+            | Commands::Docker { .. }
+            | Commands::Config { .. }
+            | Commands::Export { .. }
+            | Commands::Deploy { .. } => false,
+        }
+    }
+}
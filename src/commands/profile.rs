@@ -0,0 +1,130 @@
+use chrono::{DateTime, Local};
+use clap::Subcommand;
+use comfy_table::Cell;
+
+use crate::models::auth::ProfileStatus;
+use crate::services::AuthService;
+use crate::services::OutputRow;
+use crate::services::OutputService;
+use crate::services::UtilsService;
+use crate::utils::TABLE_DATE_FORMAT;
+use crate::utils::error::PyriteError;
+
+#[derive(Subcommand, Debug, Clone)]
+#[command(about = "Manage auth profiles", arg_required_else_help = false)]
+pub(crate) enum ProfileCommands {
+    #[command(about = "List all profiles", visible_alias = "ls")]
+    List,
+    #[command(about = "Use a profile by default")]
+    Use {
+        #[arg(help = "Profile name")]
+        name: String,
+    },
+    #[command(about = "Remove a profile and its session", visible_alias = "rm")]
+    Remove {
+        #[arg(help = "Profile name")]
+        name: String,
+    },
+}
+
+impl ProfileCommands {
+    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            ProfileCommands::List => {
+                let profiles = Self::list_profiles()?;
+                OutputService::print_list(&profiles, "No profiles found")?;
+            }
+            ProfileCommands::Use { name } => {
+                AuthService::write_default_profile(&name)?;
+
+                if AuthService::read_profile_session(&name)?.is_none() {
+                    cliclack::log::warning(format!(
+                        "Profile {} has no session, run `pyrite login` to log in",
+                        name
+                    ))?;
+                }
+
+                cliclack::outro(format!("Using profile {}", name))?;
+            }
+            ProfileCommands::Remove { name } => {
+                // Fall back to the default profile when the saved one is removed
+                let is_default = AuthService::read_default_profile()? == name;
+                if is_default {
+                    AuthService::delete_default_profile()?;
+                }
+
+                if !AuthService::delete_profile(&name)? && !is_default {
+                    return Err(PyriteError::NotFound(format!("Profile {} not found", name)).into());
+                }
+
+                cliclack::outro(format!("Removed profile {}", name))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn list_profiles() -> Result<Vec<ProfileStatus>, Box<dyn std::error::Error>> {
+        let active_profile = AuthService::get_profile();
+
+        let mut profiles = AuthService::list_profiles()?;
+        if !profiles.iter().any(|profile| profile == active_profile) {
+            profiles.push(active_profile.to_owned());
+            profiles.sort();
+        }
+
+        profiles
+            .into_iter()
+            .map(|profile| {
+                let session = AuthService::read_profile_session(&profile)?;
+
+                Ok(ProfileStatus {
+                    is_active: profile == active_profile,
+                    name: profile,
+                    email: session
+                        .as_ref()
+                        .map(|session| session.user.email.to_owned()),
+                    expires_at: session
+                        .as_ref()
+                        .and_then(|session| DateTime::from_timestamp(session.expires_at as i64, 0))
+                        .map(|expires_at| expires_at.to_rfc3339()),
+                })
+            })
+            .collect()
+    }
+}
+
+impl OutputRow for ProfileStatus {
+    fn get_header(wide: bool) -> Vec<&'static str> {
+        let mut header = vec!["Active", "Profile", "Email", "Session Expires At"];
+        if wide {
+            header.push("Expires");
+        }
+        header
+    }
+
+    fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn std::error::Error>> {
+        let expires_at = self
+            .expires_at
+            .as_deref()
+            .map(DateTime::parse_from_rfc3339)
+            .transpose()?;
+
+        let mut row = vec![
+            Cell::new(if self.is_active { "*" } else { "" }),
+            Cell::new(&self.name).fg(comfy_table::Color::White),
+            Cell::new(self.email.as_deref().unwrap_or("-")).fg(comfy_table::Color::White),
+            Cell::new(expires_at.map_or("-".to_owned(), |expires_at| {
+                expires_at
+                    .with_timezone(&Local)
+                    .format(TABLE_DATE_FORMAT)
+                    .to_string()
+            })),
+        ];
+        if wide {
+            row.push(Cell::new(expires_at.map_or("-".to_owned(), |expires_at| {
+                UtilsService::get_relative_time(expires_at.to_utc())
+            })));
+        }
+        Ok(row)
+    }
+}
@@ -2,14 +2,15 @@ use chrono::{DateTime, Local};
 use clap::Subcommand;
 use cliclack::Select;
 use comfy_table::Cell;
-use comfy_table::Table;
-use comfy_table::modifiers;
-use comfy_table::presets;
 use pyrite_client_rs::pyrite::v1::projects::v1::Project;
 
+use crate::services::OutputRow;
+use crate::services::OutputService;
 use crate::services::ProjectsService;
 use crate::services::TeamsService;
+use crate::services::UtilsService;
 use crate::utils::TABLE_DATE_FORMAT;
+use crate::utils::error::PyriteError;
 
 #[derive(Subcommand, Debug, Clone)]
 #[command(
@@ -40,18 +41,11 @@ impl ProjectsCommands {
                 };
 
                 let projects_res = ProjectsService::list_projects(team_id).await?;
-                let projects = projects_res.projects;
-                if projects.is_empty() {
-                    cliclack::outro("No projects found")?;
-                } else {
-                    let table = Self::get_projects_table(projects)?;
-                    println!("{table}");
-                }
+                OutputService::print_list(&projects_res.projects, "No projects found")?;
             }
             ProjectsCommands::Get { project_id } => {
                 let project = ProjectsService::get_project(project_id).await?;
-                let table = Self::get_projects_table(vec![project])?;
-                println!("{table}");
+                OutputService::print_item(&project)?;
             }
         }
         Ok(())
@@ -62,7 +56,7 @@ impl ProjectsCommands {
         let teams = teams_res.teams;
 
         if teams.is_empty() {
-            return Err("No teams found".into());
+            return Err(PyriteError::NotFound("No teams found".to_owned()).into());
         }
 
         let items = teams
@@ -85,41 +79,42 @@ impl ProjectsCommands {
 
         Ok(if !res.is_empty() { Some(res) } else { None })
     }
+}
 
-    fn get_projects_table(projects: Vec<Project>) -> Result<Table, Box<dyn std::error::Error>> {
-        let mut table = Table::new();
-
-        table
-            .load_preset(presets::UTF8_FULL)
-            .apply_modifier(modifiers::UTF8_ROUND_CORNERS)
-            .set_header(vec![
-                "Project Id",
-                "Project Name",
-                "Team Id",
-                "Created At",
-                "Updated At",
-            ]);
-
-        for project in projects {
-            let created_at = DateTime::parse_from_rfc3339(project.created_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
+impl OutputRow for Project {
+    fn get_header(wide: bool) -> Vec<&'static str> {
+        let mut header = vec![
+            "Project Id",
+            "Project Name",
+            "Team Id",
+            "Created At",
+            "Updated At",
+        ];
+        if wide {
+            header.push("Age");
+        }
+        header
+    }
 
-            let updated_at = DateTime::parse_from_rfc3339(project.updated_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
+    fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn std::error::Error>> {
+        let created_at =
+            DateTime::parse_from_rfc3339(self.created_at.as_str())?.with_timezone(&Local);
+        let updated_at =
+            DateTime::parse_from_rfc3339(self.updated_at.as_str())?.with_timezone(&Local);
 
-            table.add_row(vec![
-                Cell::new(project.id),
-                Cell::new(project.name).fg(comfy_table::Color::White),
-                Cell::new(project.team_id),
-                Cell::new(created_at),
-                Cell::new(updated_at),
-            ]);
+        let mut row = vec![
+            Cell::new(&self.id),
+            Cell::new(&self.name).fg(comfy_table::Color::White),
+            Cell::new(&self.team_id),
+            Cell::new(created_at.format(TABLE_DATE_FORMAT)),
+            Cell::new(updated_at.format(TABLE_DATE_FORMAT)),
+        ];
+        if wide {
+            row.push(Cell::new(UtilsService::get_relative_time(
+                created_at.to_utc(),
+            )));
         }
 
-        Ok(table)
+        Ok(row)
     }
 }
@@ -2,16 +2,16 @@ use chrono::{DateTime, Local};
 use clap::Subcommand;
 use cliclack::Select;
 use comfy_table::Cell;
-use comfy_table::Table;
-use comfy_table::modifiers;
-use comfy_table::presets;
 use pyrite_client_rs::pyrite::v1::services::v1::common::v1::Service;
 
+use crate::services::OutputRow;
+use crate::services::OutputService;
 use crate::services::ProjectsService;
 use crate::services::ServicesService;
 use crate::services::TeamsService;
 use crate::services::UtilsService;
 use crate::utils::TABLE_DATE_FORMAT;
+use crate::utils::error::PyriteError;
 
 #[derive(Subcommand, Debug, Clone)]
 #[command(
@@ -53,29 +53,22 @@ impl ServicesCommands {
                 };
 
                 let services_res = ServicesService::list_services(team_id, project_id).await?;
-                let services = services_res.services;
-                if services.is_empty() {
-                    cliclack::outro("No services found")?;
-                } else {
-                    let table = Self::get_services_table(services)?;
-                    println!("{table}");
-                }
+                OutputService::print_list(&services_res.services, "No services found")?;
             }
             ServicesCommands::Get { service_id } => {
-                let project = ServicesService::get_service(service_id).await?;
-                let table = Self::get_services_table(vec![project])?;
-                println!("{table}");
+                let service = ServicesService::get_service(service_id).await?;
+                OutputService::print_item(&service)?;
             }
         }
         Ok(())
     }
 
-    async fn select_team() -> Result<Option<String>, Box<dyn std::error::Error>> {
+    pub(crate) async fn select_team() -> Result<Option<String>, Box<dyn std::error::Error>> {
         let teams_res = TeamsService::list_teams().await?;
         let teams = teams_res.teams;
 
         if teams.is_empty() {
-            return Err("No teams found".into());
+            return Err(PyriteError::NotFound("No teams found".to_owned()).into());
         }
 
         let items = teams
@@ -99,14 +92,14 @@ impl ServicesCommands {
         Ok(if !res.is_empty() { Some(res) } else { None })
     }
 
-    async fn select_project(
+    pub(crate) async fn select_project(
         team_id: &Option<String>,
     ) -> Result<Option<String>, Box<dyn std::error::Error>> {
         let projects_res = ProjectsService::list_projects(team_id.clone()).await?;
         let projects = projects_res.projects;
 
         if projects.is_empty() {
-            return Err("No projects found".into());
+            return Err(PyriteError::NotFound("No projects found".to_owned()).into());
         }
 
         let items = projects
@@ -127,46 +120,47 @@ impl ServicesCommands {
 
         Ok(if !res.is_empty() { Some(res) } else { None })
     }
+}
+
+impl OutputRow for Service {
+    fn get_header(wide: bool) -> Vec<&'static str> {
+        let mut header = vec![
+            "Service Id",
+            "Project Id",
+            "Service Name",
+            "Type",
+            "Status",
+            "Created At",
+            "Updated At",
+        ];
+        if wide {
+            header.push("Age");
+        }
+        header
+    }
 
-    fn get_services_table(services: Vec<Service>) -> Result<Table, Box<dyn std::error::Error>> {
-        let mut table = Table::new();
-
-        table
-            .load_preset(presets::UTF8_FULL)
-            .apply_modifier(modifiers::UTF8_ROUND_CORNERS)
-            .set_header(vec![
-                "Service Id",
-                "Project Id",
-                "Service Name",
-                "Type",
-                "Status",
-                "Created At",
-                "Updated At",
-            ]);
-
-        for service in services {
-            let created_at = DateTime::parse_from_rfc3339(service.created_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
-
-            let updated_at = DateTime::parse_from_rfc3339(service.updated_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
-
-            table.add_row(vec![
-                Cell::new(service.id),
-                Cell::new(service.project_id),
-                Cell::new(service.name).fg(comfy_table::Color::White),
-                Cell::new(service.r#type.to_uppercase()),
-                Cell::new(UtilsService::get_service_status_label(service.status))
-                    .fg(UtilsService::get_service_status_color(service.status)),
-                Cell::new(created_at),
-                Cell::new(updated_at),
-            ]);
+    fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn std::error::Error>> {
+        let created_at =
+            DateTime::parse_from_rfc3339(self.created_at.as_str())?.with_timezone(&Local);
+        let updated_at =
+            DateTime::parse_from_rfc3339(self.updated_at.as_str())?.with_timezone(&Local);
+
+        let mut row = vec![
+            Cell::new(&self.id),
+            Cell::new(&self.project_id),
+            Cell::new(&self.name).fg(comfy_table::Color::White),
+            Cell::new(self.r#type.to_uppercase()),
+            Cell::new(UtilsService::get_service_status_label(self.status))
+                .fg(UtilsService::get_service_status_color(self.status)),
+            Cell::new(created_at.format(TABLE_DATE_FORMAT)),
+            Cell::new(updated_at.format(TABLE_DATE_FORMAT)),
+        ];
+        if wide {
+            row.push(Cell::new(UtilsService::get_relative_time(
+                created_at.to_utc(),
+            )));
         }
 
-        Ok(table)
+        Ok(row)
     }
 }
@@ -1,12 +1,12 @@
 use chrono::{DateTime, Local};
 use clap::Subcommand;
 use comfy_table::Cell;
-use comfy_table::Table;
-use comfy_table::modifiers;
-use comfy_table::presets;
 use pyrite_client_rs::pyrite::v1::teams::v1::Team;
 
+use crate::services::OutputRow;
+use crate::services::OutputService;
 use crate::services::TeamsService;
+use crate::services::UtilsService;
 use crate::utils::TABLE_DATE_FORMAT;
 
 #[derive(Subcommand, Debug, Clone)]
@@ -30,55 +30,58 @@ impl TeamsCommands {
         match self {
             TeamsCommands::List => {
                 let teams_res = TeamsService::list_teams().await?;
-                let table = Self::get_teams_table(teams_res.teams)?;
-                println!("{table}");
+                OutputService::print_list(&teams_res.teams, "No teams found")?;
             }
             TeamsCommands::Get { team_id } => {
                 let team = TeamsService::get_team(team_id).await?;
-                let table = Self::get_teams_table(vec![team])?;
-                println!("{table}");
+                OutputService::print_item(&team)?;
             }
         }
         Ok(())
     }
+}
 
-    fn get_teams_table(teams: Vec<Team>) -> Result<Table, Box<dyn std::error::Error>> {
-        let mut table = Table::new();
-
-        table
-            .load_preset(presets::UTF8_FULL)
-            .apply_modifier(modifiers::UTF8_ROUND_CORNERS)
-            .set_header(vec![
-                "Team Id",
-                "Team Name",
-                "Subscription",
-                "Owner",
-                "Created At",
-                "Updated At",
-            ]);
-
-        for team in teams {
-            let owner = team.meta.map_or(team.owner, |meta| meta.owner_email);
-            let created_at = DateTime::parse_from_rfc3339(team.created_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
+impl OutputRow for Team {
+    fn get_header(wide: bool) -> Vec<&'static str> {
+        let mut header = vec![
+            "Team Id",
+            "Team Name",
+            "Subscription",
+            "Owner",
+            "Created At",
+            "Updated At",
+        ];
+        if wide {
+            header.extend(["Owner Id", "Age"]);
+        }
+        header
+    }
 
-            let updated_at = DateTime::parse_from_rfc3339(team.updated_at.as_str())?
-                .with_timezone(&Local)
-                .format(TABLE_DATE_FORMAT)
-                .to_string();
+    fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn std::error::Error>> {
+        let owner = self
+            .meta
+            .as_ref()
+            .map_or(self.owner.to_owned(), |meta| meta.owner_email.to_owned());
+        let created_at =
+            DateTime::parse_from_rfc3339(self.created_at.as_str())?.with_timezone(&Local);
+        let updated_at =
+            DateTime::parse_from_rfc3339(self.updated_at.as_str())?.with_timezone(&Local);
 
-            table.add_row(vec![
-                Cell::new(team.id),
-                Cell::new(team.name).fg(comfy_table::Color::White),
-                Cell::new(team.subscription.to_uppercase()).fg(comfy_table::Color::White),
-                Cell::new(owner).fg(comfy_table::Color::White),
-                Cell::new(created_at),
-                Cell::new(updated_at),
+        let mut row = vec![
+            Cell::new(&self.id),
+            Cell::new(&self.name).fg(comfy_table::Color::White),
+            Cell::new(self.subscription.to_uppercase()).fg(comfy_table::Color::White),
+            Cell::new(owner).fg(comfy_table::Color::White),
+            Cell::new(created_at.format(TABLE_DATE_FORMAT)),
+            Cell::new(updated_at.format(TABLE_DATE_FORMAT)),
+        ];
+        if wide {
+            row.extend([
+                Cell::new(&self.owner),
+                Cell::new(UtilsService::get_relative_time(created_at.to_utc())),
             ]);
         }
 
-        Ok(table)
+        Ok(row)
     }
 }
@@ -1,7 +1,12 @@
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, error::ErrorKind, parser::ValueSource};
 use cliclack::set_theme;
-use commands::{Cli, Commands, auth::AuthCommands, deploy::DeployCommands};
-use utils::PyriteTheme;
+use commands::{
+    Cli, Commands, auth::AuthCommands, deploy::DeployCommands, export::ExportCommands,
+    init::InitCommands,
+};
+use console::style;
+use services::{AuthService, EndpointsService, OutputService};
+use utils::{PyriteTheme, error::PyriteError};
 
 pub mod commands;
 pub mod models;
@@ -9,20 +14,98 @@ mod services;
 pub mod utils;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    set_theme(PyriteTheme);
+async fn main() {
+    if let Err(err) = run().await {
+        let err = PyriteError::from(err);
 
-    let args = Cli::parse();
+        if !matches!(err, PyriteError::ChangesPending(_)) {
+            eprintln!("{} {}", style("error:").red().bold(), err);
+            if let Some(hint) = err.hint() {
+                eprintln!("{} {}", style("hint:").cyan().bold(), hint);
+            }
+        }
+
+        std::process::exit(err.exit_code());
+    }
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    // Usage errors exit with 6 like other invalid input, clap would exit with 2
+    let matches = match Cli::command().try_get_matches() {
+        Ok(matches) => matches,
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion
+            ) =>
+        {
+            err.exit()
+        }
+        Err(err) if err.kind() == ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+            err.print()?;
+            return Err(PyriteError::Validation("A command is required".to_owned()).into());
+        }
+        Err(err) => return Err(PyriteError::from(err).into()),
+    };
+    let args = Cli::from_arg_matches(&matches).map_err(PyriteError::from)?;
+
+    // Like `conflicts_with`, but `--format` still overrides an exported `PYRITE_OUTPUT`
+    let is_command_line = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if is_command_line("output") && is_command_line("format") {
+        let err = Cli::command().error(
+            ErrorKind::ArgumentConflict,
+            "the argument '--format <FORMAT>' cannot be used with '--output <OUTPUT>'",
+        );
+        return Err(PyriteError::from(err).into());
+    }
+
+    EndpointsService::set_endpoints(args.endpoints.into())?;
+    AuthService::set_profile(args.profile)?;
+    OutputService::set_output(args.output);
+    OutputService::set_format(args.format);
+    set_theme(PyriteTheme {
+        profile: AuthService::get_profile().to_owned(),
+    });
+
+    // Only flags passed on the command line, `PYRITE_OUTPUT` is set for every command
+    if !args.cmd.has_output() {
+        for flag in ["output", "format"] {
+            if is_command_line(flag) {
+                cliclack::log::warning(format!("--{} is ignored by this command", flag))?;
+            }
+        }
+    }
 
     match args.cmd {
-        Commands::Login => AuthCommands::login().await?,
-        Commands::Logout => AuthCommands::logout().await?,
+        Commands::Login {
+            provider,
+            no_browser,
+            timeout,
+        } => AuthCommands::login(provider, no_browser, timeout).await?,
+        Commands::Logout { all, all_profiles } => AuthCommands::logout(all, all_profiles).await?,
+        Commands::Whoami => AuthCommands::status().await?,
+        Commands::Auth { auth_cmd } => auth_cmd.run().await?,
+        Commands::Init => InitCommands::run().await?,
         Commands::Docker { docker_cmd } => docker_cmd.run().await?,
         Commands::Teams { teams_cmd } => teams_cmd.run().await?,
         Commands::Projects { projects_cmd } => projects_cmd.run().await?,
         Commands::Services { services_cmd } => services_cmd.run().await?,
         Commands::Environments { environments_cmd } => environments_cmd.run().await?,
-        Commands::Deploy { file } => DeployCommands::run(file).await?,
+        Commands::Profile { profile_cmd } => profile_cmd.run().await?,
+        Commands::Config { config_cmd } => config_cmd.run().await?,
+        Commands::Export {
+            project_id,
+            file,
+            include_secrets,
+            force,
+        } => ExportCommands::run(project_id, file, include_secrets, force).await?,
+        Commands::Deploy {
+            file,
+            env,
+            plan,
+            wait,
+            timeout,
+        } => DeployCommands::run(file, env, plan, wait, timeout).await?,
     }
 
     Ok(())
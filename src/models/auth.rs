@@ -1,6 +1,98 @@
-use serde::Deserialize;
+use std::fmt::Display;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use supabase_auth::models::Provider;
 
 #[derive(Deserialize)]
 pub struct AuthParams {
     pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RefreshStatus {
+    NotNeeded,
+    // Close to expiry, `pyrite auth status` refreshed and saved it
+    Refreshed,
+    // API tokens can't be refreshed
+    NotAvailable,
+}
+
+impl Display for RefreshStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            RefreshStatus::NotNeeded => "Not needed",
+            RefreshStatus::Refreshed => "Refreshed",
+            RefreshStatus::NotAvailable => "Not available",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+// A row of `pyrite profile list`, dates are RFC 3339 like the API's
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileStatus {
+    pub name: String,
+    pub is_active: bool,
+    pub email: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+// The session shown by `pyrite auth status`
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    pub profile: String,
+    pub email: String,
+    pub user_id: String,
+    pub expires_at: Option<String>,
+    pub refresh_status: RefreshStatus,
+    // None when the teams weren't listed
+    pub teams: Option<Vec<String>>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginProvider {
+    Github,
+    Gitlab,
+    Google,
+    Bitbucket,
+    Azure,
+    // Email and password
+    Email,
+    // A one-time code sent by email
+    MagicLink,
+}
+
+impl LoginProvider {
+    pub fn get_oauth_provider(&self) -> Option<Provider> {
+        match self {
+            LoginProvider::Github => Some(Provider::Github),
+            LoginProvider::Gitlab => Some(Provider::Gitlab),
+            LoginProvider::Google => Some(Provider::Google),
+            LoginProvider::Bitbucket => Some(Provider::Bitbucket),
+            LoginProvider::Azure => Some(Provider::Azure),
+            LoginProvider::Email | LoginProvider::MagicLink => None,
+        }
+    }
+}
+
+impl Display for LoginProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let provider = match self {
+            LoginProvider::Github => "GitHub",
+            LoginProvider::Gitlab => "GitLab",
+            LoginProvider::Google => "Google",
+            LoginProvider::Bitbucket => "Bitbucket",
+            LoginProvider::Azure => "Azure",
+            LoginProvider::Email => "Email and password",
+            LoginProvider::MagicLink => "Email login code",
+        };
+        write!(f, "{}", provider)
+    }
 }
@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+// Endpoint and connection overrides, from CLI flags, env vars or `~/.pyrite/config.toml`
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct EndpointsConfig {
+    pub api_url: Option<String>,
+    pub workflows_url: Option<String>,
+    pub supabase_url: Option<String>,
+    pub supabase_key: Option<String>,
+    // Seconds
+    pub connect_timeout: Option<u64>,
+    pub request_timeout: Option<u64>,
+    pub retries: Option<u32>,
+}
+
+impl EndpointsConfig {
+    // Values of `self` take precedence
+    pub fn merge(self, other: EndpointsConfig) -> Self {
+        Self {
+            api_url: self.api_url.or(other.api_url),
+            workflows_url: self.workflows_url.or(other.workflows_url),
+            supabase_url: self.supabase_url.or(other.supabase_url),
+            supabase_key: self.supabase_key.or(other.supabase_key),
+            connect_timeout: self.connect_timeout.or(other.connect_timeout),
+            request_timeout: self.request_timeout.or(other.request_timeout),
+            retries: self.retries.or(other.retries),
+        }
+    }
+}
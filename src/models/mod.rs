@@ -1,4 +1,7 @@
 pub mod auth;
+pub mod endpoints;
 pub mod options;
+pub mod output;
+pub mod plan;
 pub mod pyrite_toml;
 pub mod vars;
@@ -0,0 +1,12 @@
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    // Table with extra columns
+    Wide,
+    Json,
+    Yaml,
+    Csv,
+}
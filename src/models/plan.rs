@@ -0,0 +1,21 @@
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PlanAction {
+    Create,
+    Update,
+    NoOp,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct FieldChange {
+    pub field: String,
+    pub current: String,
+    pub desired: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ServicePlan {
+    pub name: String,
+    pub environment: String,
+    pub action: PlanAction,
+    pub changes: Vec<FieldChange>,
+}
@@ -5,32 +5,108 @@ use pyrite_client_rs::pyrite::v1::services::v1::deployments::v1::{
     DeploymentFileDto, DeploymentHealthCheckDto, DeploymentPortDto, DeploymentRegionDto,
     DeploymentVolumeDto,
 };
+use schemars::JsonSchema;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+use crate::utils::schema::{
+    file_list_schema, health_check_list_schema, port_list_schema, region_list_schema,
+    volume_list_schema,
+};
+
+// Unknown keys are denied by the schema, `PyriteTomlService` checks it before deserializing the
+// merged document, so overlays and `[environments]` don't have to be fields
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub(crate) struct PyriteToml {
     pub project_id: String,
     pub services: Vec<TomlService>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub(crate) struct TomlService {
     pub name: String,
     pub environment: String,
     pub r#type: String,
-    pub image: String,
     pub plan: String,
-    pub runtime: String,
+    // Docker
+    pub image: Option<String>,
+    pub runtime: Option<String>,
     pub command: Option<String>,
     pub args: Option<Vec<String>>,
+    #[serde(default)]
+    #[schemars(schema_with = "region_list_schema")]
     pub regions: Option<Vec<DeploymentRegionDto>>,
+    #[serde(default)]
+    #[schemars(schema_with = "port_list_schema")]
     pub ports: Option<Vec<DeploymentPortDto>>,
+    #[serde(default)]
+    #[schemars(schema_with = "health_check_list_schema")]
     pub health_checks: Option<Vec<DeploymentHealthCheckDto>>,
+    #[serde(default)]
+    #[schemars(schema_with = "volume_list_schema")]
     pub volumes: Option<Vec<DeploymentVolumeDto>>,
+    #[serde(default)]
+    #[schemars(schema_with = "file_list_schema")]
     pub files: Option<Vec<DeploymentFileDto>>,
+    #[schemars(with = "Option<HashMap<String, serde_json::Value>>")]
     pub env: Option<HashMap<String, Value>>,
+    pub env_file: Option<String>,
     pub with_project_env: Option<bool>,
     pub registry_id: Option<String>,
     pub is_private: Option<bool>,
     pub is_privileged: Option<bool>,
+    // Postgres
+    pub version: Option<String>,
+    pub storage: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ServiceType {
+    Docker,
+    Postgres,
+}
+
+impl ServiceType {
+    // Shared by deploy and `pyrite config validate`, so both reject the same types
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "docker" => Ok(ServiceType::Docker),
+            "postgres" => Ok(ServiceType::Postgres),
+            _ => Err(format!(
+                "type {} is not supported, expected docker or postgres",
+                name
+            )),
+        }
+    }
+
+    pub fn get_required_keys(&self) -> &'static [&'static str] {
+        match self {
+            ServiceType::Docker => &["image", "runtime"],
+            ServiceType::Postgres => &["version"],
+        }
+    }
+
+    // Keys of the other type, the deployment config of this type has no field for them
+    pub fn get_unsupported_keys(&self) -> &'static [&'static str] {
+        match self {
+            ServiceType::Docker => &["version", "storage"],
+            ServiceType::Postgres => &[
+                "image",
+                "runtime",
+                "command",
+                "args",
+                "ports",
+                "health_checks",
+                "volumes",
+                "files",
+                "env",
+                "env_file",
+                "with_project_env",
+                "registry_id",
+                "is_private",
+                "is_privileged",
+            ],
+        }
+    }
 }
@@ -1,25 +1,61 @@
-use std::{collections::HashMap, error::Error, fs, path::PathBuf, sync::Arc};
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
 
 use axum::{
     Router,
     extract::{Query, State},
-    response::{IntoResponse, Redirect},
+    response::{Html, IntoResponse},
     routing::get,
 };
 use base64::prelude::*;
 use chrono::Utc;
-use rust_dotenv::dotenv::DotEnv;
-use supabase_auth::models::{AuthClient, Session};
-use tokio::net::TcpListener;
-use tonic::metadata::MetadataMap;
+use handlebars::Handlebars;
+use serde_json::json;
+use supabase_auth::models::{
+    AuthClient, LoginEmailOtpParams, LogoutScope, OtpType, Session, VerifyEmailOtpParams,
+    VerifyOtpParams,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{Notify, mpsc},
+};
+
+use crate::{models::auth::AuthParams, utils::error::PyriteError};
+
+use super::EndpointsService;
+
+// API token for non-interactive use, e.g. in CI
+pub(crate) const PYRITE_TOKEN_ENV: &str = "PYRITE_TOKEN";
+
+pub(crate) const DEFAULT_PROFILE: &str = "default";
 
-use crate::models::auth::AuthParams;
-use tokio::sync::Notify;
+// Expiry of API tokens without an `exp` claim, the largest timestamp that fits an `i64`
+const NO_EXPIRY: u64 = i64::MAX as u64;
+
+// Sessions are refreshed this long before they expire
+const SESSION_REFRESH_MARGIN_SECS: u64 = 5 * 60;
+
+// Session attached to API requests, see `AuthService::get_cached_session`
+static SESSION_CACHE: RwLock<Option<Session>> = RwLock::new(None);
+
+// The profile selected for this process, see `AuthService::set_profile`
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+pub(crate) const AUTH_CALLBACK_PORT: u16 = 3456;
+
+const CALLBACK_PAGE: &str = include_str!("callback.html");
 
 #[derive(Clone)]
 struct AppState {
-    shutdown_notify: Arc<Notify>,
+    result_tx: mpsc::Sender<Result<Session, String>>,
     code_verifier: String,
+    csrf_state: String,
 }
 
 #[derive(Debug, Clone)]
@@ -27,121 +63,395 @@ pub(crate) struct AuthService;
 
 impl AuthService {
     pub fn get_auth_client() -> AuthClient {
-        // Compile-time values (set by CI)
-        let project_url = option_env!("SUPABASE_URL");
-        let api_key = option_env!("SUPABASE_API_KEY");
-
-        if let (Some(project_url), Some(api_key)) = (project_url, api_key) {
-            return AuthClient::new(project_url.to_string(), api_key.to_string(), "");
-        }
-
-        // Fallback to dotenv in local dev
-        let dot_env = DotEnv::new(if cfg!(debug_assertions) {
-            "local"
-        } else {
-            "prod"
-        });
-
-        let project_url = dot_env.get_var("SUPABASE_URL".to_owned()).unwrap();
-        let api_key = dot_env.get_var("SUPABASE_API_KEY".to_owned()).unwrap();
-
+        let (project_url, api_key) = EndpointsService::get_supabase();
         AuthClient::new(project_url, api_key, "")
     }
 
     pub async fn get_session() -> Result<Session, Box<dyn Error>> {
-        let auth_client = Self::get_auth_client();
         let session = Self::read_session()
             .await?
-            .ok_or("No active session found. Please log in to continue.")?;
+            .ok_or_else(|| PyriteError::Auth("No active session found".to_owned()))?;
 
-        // Return the session if it's not expired
-        if session.expires_at >= Utc::now().timestamp() as u64 {
+        // Return the session if it's not close to expiry
+        if !Self::needs_refresh(&session) {
             return Ok(session);
         }
 
-        // Refresh the session if it's expired
-        if let Ok(new_session) = auth_client.refresh_session(&session.refresh_token).await {
-            // Write the new session to file
-            Self::write_session(&new_session)?;
+        Self::refresh_session(&session).await
+    }
+
+    // Refreshes `session`, unless another process has refreshed it already
+    pub async fn refresh_session(session: &Session) -> Result<Session, Box<dyn Error>> {
+        // Refresh tokens are single use, so only one process may refresh at a time. Others
+        // wait for the lock and pick up the refreshed session.
+        let _lock = Self::lock_session().await?;
+        let current_session = Self::read_session()
+            .await?
+            .ok_or_else(|| PyriteError::Auth("No active session found".to_owned()))?;
 
-            return Ok(new_session);
+        if current_session.access_token != session.access_token
+            && !Self::needs_refresh(&current_session)
+        {
+            return Ok(current_session);
         }
 
-        Self::delete_session()?;
-        Err("Session expired. Please log in again to continue.".into())
+        let auth_client = Self::get_auth_client();
+        match auth_client
+            .refresh_session(&current_session.refresh_token)
+            .await
+        {
+            Ok(new_session) => {
+                // Write the new session to file
+                Self::write_session(&new_session)?;
+
+                Ok(new_session)
+            }
+            // Refreshed early, the current session is still usable
+            Err(_) if !Self::is_expired(&current_session) => Ok(current_session),
+            Err(_) => {
+                Self::delete_session()?;
+                Err(PyriteError::Auth("The session expired".to_owned()).into())
+            }
+        }
+    }
+
+    // The session used by API clients, read once per process
+    pub async fn get_cached_session() -> Result<Session, Box<dyn Error>> {
+        if let Some(session) = SESSION_CACHE.read().unwrap().as_ref()
+            && !Self::needs_refresh(session)
+        {
+            return Ok(session.clone());
+        }
+
+        let session = Self::get_active_session().await?;
+        *SESSION_CACHE.write().unwrap() = Some(session.clone());
+
+        Ok(session)
+    }
+
+    // Called when the API rejects the cached session
+    pub async fn refresh_cached_session() -> Result<Session, Box<dyn Error>> {
+        if Self::get_env_token().is_some() {
+            return Err(PyriteError::Auth("The API token is invalid or expired".to_owned()).into());
+        }
+
+        let cached_session = SESSION_CACHE.read().unwrap().clone();
+        let session = match cached_session {
+            Some(cached_session) => Self::refresh_session(&cached_session).await?,
+            None => Self::get_session().await?,
+        };
+        *SESSION_CACHE.write().unwrap() = Some(session.clone());
+
+        Ok(session)
+    }
+
+    pub fn get_cached_auth_header() -> Result<Option<(String, String)>, Box<dyn Error>> {
+        match SESSION_CACHE.read().unwrap().as_ref() {
+            Some(session) => Ok(Some(Self::get_auth_header(session)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_expired(session: &Session) -> bool {
+        session.expires_at < Utc::now().timestamp() as u64
+    }
+
+    fn needs_refresh(session: &Session) -> bool {
+        session.expires_at < Utc::now().timestamp() as u64 + SESSION_REFRESH_MARGIN_SECS
     }
 
-    pub async fn get_metadata() -> Result<MetadataMap, Box<dyn Error>> {
-        let session = Self::get_session().await?;
-        let cookie_key = if cfg!(debug_assertions) {
-            "sb-127-auth-token"
+    // Advisory lock held while the session is refreshed, released when the file is dropped
+    async fn lock_session() -> Result<File, Box<dyn Error>> {
+        let path_buf = Self::get_profile_dir(Self::get_profile()).join("session.lock");
+        fs::create_dir_all(path_buf.parent().unwrap())?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path_buf)?;
+
+        let file = tokio::task::spawn_blocking(move || file.lock().map(|_| file)).await??;
+        Ok(file)
+    }
+
+    // Builds an in-memory session from an API token, it is never written to disk
+    pub async fn get_token_session(token: &str) -> Result<Session, Box<dyn Error>> {
+        let auth_client = Self::get_auth_client();
+        let user = auth_client
+            .get_user(token)
+            .await
+            .map_err(|_| PyriteError::Auth("The API token is invalid or expired".to_owned()))?;
+
+        Ok(Session {
+            access_token: token.to_owned(),
+            token_type: "bearer".to_owned(),
+            expires_at: Self::get_token_expiry(token).unwrap_or(NO_EXPIRY),
+            user,
+            ..Default::default()
+        })
+    }
+
+    pub fn get_env_token() -> Option<String> {
+        std::env::var(PYRITE_TOKEN_ENV)
+            .ok()
+            .map(|token| token.trim().to_owned())
+            .filter(|token| !token.is_empty())
+    }
+
+    // Reads the `exp` claim of a JWT without verifying it
+    fn get_token_expiry(token: &str) -> Option<u64> {
+        let payload = token.split('.').nth(1)?;
+        let payload = BASE64_URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .ok()?;
+        let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+        claims.get("exp")?.as_u64()
+    }
+
+    // An API token takes precedence over the session file
+    pub async fn get_active_session() -> Result<Session, Box<dyn Error>> {
+        match Self::get_env_token() {
+            Some(token) => Self::get_token_session(&token).await,
+            None => Self::get_session().await,
+        }
+    }
+
+    // Returns the name and value of the auth cookie header sent with every request
+    pub fn get_auth_header(session: &Session) -> Result<(String, String), Box<dyn Error>> {
+        let cookie_key = EndpointsService::get_cookie_key()?;
+        let cookie_value = format!(
+            "base64-{}",
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_string(session)?)
+        );
+        Ok((cookie_key, cookie_value))
+    }
+
+    pub async fn login_with_password(
+        email: &str,
+        password: &str,
+    ) -> Result<Session, Box<dyn Error>> {
+        let auth_client = Self::get_auth_client();
+        let session = auth_client
+            .login_with_email(email, password)
+            .await
+            .map_err(|err| PyriteError::Auth(format!("Failed to login: {}", err)))?;
+
+        Self::write_session(&session)?;
+
+        Ok(session)
+    }
+
+    // Sends an email with a magic link and a one-time code, only the code can be used here
+    pub async fn send_login_code(email: &str) -> Result<(), Box<dyn Error>> {
+        let auth_client = Self::get_auth_client();
+        let options = LoginEmailOtpParams {
+            should_create_user: Some(false),
+            ..Default::default()
+        };
+
+        auth_client
+            .send_email_with_otp(email, Some(options))
+            .await
+            .map_err(|err| PyriteError::Auth(format!("Failed to send the login code: {}", err)))?;
+
+        Ok(())
+    }
+
+    pub async fn verify_login_code(email: &str, code: &str) -> Result<Session, Box<dyn Error>> {
+        let auth_client = Self::get_auth_client();
+        let params = VerifyOtpParams::Email(VerifyEmailOtpParams {
+            email: email.to_owned(),
+            token: code.to_owned(),
+            otp_type: OtpType::Email,
+            options: None,
+        });
+        let session = auth_client.verify_otp(params).await.map_err(|err| {
+            PyriteError::Auth(format!("Failed to verify the login code: {}", err))
+        })?;
+
+        Self::write_session(&session)?;
+
+        Ok(session)
+    }
+
+    // Revokes the session on the auth server, or every session of the user with `all`
+    pub async fn revoke_session(session: &Session, all: bool) -> Result<(), Box<dyn Error>> {
+        let auth_client = Self::get_auth_client();
+
+        // Revoking needs a valid access token
+        let access_token = if Self::is_expired(session) {
+            auth_client
+                .refresh_session(&session.refresh_token)
+                .await?
+                .access_token
+        } else {
+            session.access_token.to_owned()
+        };
+
+        let scope = if all {
+            LogoutScope::Global
         } else {
-            "sb-uziefrixdcjogieucnel-auth-token"
+            LogoutScope::Local
         };
-        let headers = HashMap::from([(
-            cookie_key.to_owned(),
-            format!(
-                "base64-{}",
-                BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_string(&session)?)
-            ),
-        )]);
-        let metadata = MetadataMap::from_headers(TryFrom::try_from(&headers).unwrap());
-        Ok(metadata)
+        auth_client.logout(Some(scope), &access_token).await?;
+
+        Ok(())
+    }
+
+    pub async fn exchange_code_for_session(
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<Session, Box<dyn Error>> {
+        let auth_client = Self::get_auth_client();
+        let session = auth_client
+            .exchange_code_for_session(code, code_verifier)
+            .await
+            .map_err(|err| {
+                PyriteError::Auth(format!(
+                    "Failed to exchange the authorization code: {}",
+                    err
+                ))
+            })?;
+
+        Self::write_session(&session)?;
+
+        Ok(session)
     }
 
     async fn auth_callback_handler(
         State(state): State<Arc<AppState>>,
         Query(params): Query<AuthParams>,
     ) -> impl IntoResponse {
-        if let Some(code) = params.code {
-            let auth_client = Self::get_auth_client();
-            let session = auth_client
-                .exchange_code_for_session(&code, &state.code_verifier)
-                .await;
-
-            // Trigger the graceful shutdown
-            state.shutdown_notify.notify_one();
-
-            match session {
-                Ok(session) => {
-                    let _ = Self::write_session(&session);
-                    // return "Login successful you can close this window now".into_response();
-                    return Redirect::temporary("https://www.pyrite.cloud").into_response();
-                }
-                Err(err) => {
-                    return format!("Failed to login: {}", err).into_response();
-                }
-            }
+        let result = Self::process_callback(&state, params).await;
+
+        let page = Self::render_callback_page(&result);
+
+        // The first callback ends the login, the server is shut down after responding
+        let _ = state.result_tx.try_send(result);
+
+        page
+    }
+
+    async fn process_callback(state: &AppState, params: AuthParams) -> Result<Session, String> {
+        if let Some(err) = params.error_description.or(params.error) {
+            return Err(format!("The provider returned an error: {}", err));
         }
 
-        "Something went wrong. Please try again.".into_response()
+        // The state is part of the redirect URL, a mismatch means the callback wasn't
+        // started by this login
+        if params.state.as_deref() != Some(state.csrf_state.as_str()) {
+            return Err("Invalid state parameter, please try logging in again".to_owned());
+        }
+
+        let code = params
+            .code
+            .ok_or("The callback is missing the authorization code")?;
+
+        Self::exchange_code_for_session(&code, &state.code_verifier)
+            .await
+            .map_err(|err| err.to_string())
     }
 
-    pub async fn start_auth_server(code_verifier: String) -> Result<(), Box<dyn Error>> {
+    fn render_callback_page(result: &Result<Session, String>) -> Html<String> {
+        let data = match result {
+            Ok(session) => json!({
+                "success": true,
+                "title": "Logged in",
+                "message": format!("Logged in as {}, you can close this window now.", session.user.email),
+            }),
+            Err(err) => json!({
+                "success": false,
+                "title": "Login failed",
+                "message": err,
+            }),
+        };
+
+        let page = Handlebars::new()
+            .render_template(CALLBACK_PAGE, &data)
+            .unwrap_or_else(|err| err.to_string());
+
+        Html(page)
+    }
+
+    // Only this port is allow-listed as a redirect URL, another one would be rejected by the
+    // provider after logging in
+    pub async fn bind_auth_server() -> Result<TcpListener, Box<dyn Error>> {
+        TcpListener::bind(("127.0.0.1", AUTH_CALLBACK_PORT))
+            .await
+            .map_err(|err| {
+                PyriteError::Other(format!(
+                    "Failed to start the login server, port {} is in use ({}), free it or log in with --no-browser",
+                    AUTH_CALLBACK_PORT, err
+                ))
+                .into()
+            })
+    }
+
+    pub fn get_callback_url(port: u16, csrf_state: &str) -> String {
+        format!(
+            "http://127.0.0.1:{}/auth/callback?state={}",
+            port, csrf_state
+        )
+    }
+
+    // Waits for the OAuth callback, the login is cancelled on timeout or Ctrl-C
+    pub async fn start_auth_server(
+        listener: TcpListener,
+        code_verifier: String,
+        csrf_state: String,
+        timeout: Duration,
+    ) -> Result<Session, Box<dyn Error>> {
         let shutdown_notify = Arc::new(Notify::new());
+        let (result_tx, mut result_rx) = mpsc::channel(1);
 
         let state: Arc<AppState> = Arc::new(AppState {
-            shutdown_notify: shutdown_notify.clone(),
+            result_tx,
             code_verifier,
+            csrf_state,
         });
 
         let router = Router::<Arc<AppState>>::new()
             .route("/auth/callback", get(AuthService::auth_callback_handler))
             .with_state(state.clone());
 
-        let listener = TcpListener::bind("127.0.0.1:3456").await.unwrap();
+        let server_shutdown_notify = shutdown_notify.clone();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async move {
+                    server_shutdown_notify.notified().await;
+                })
+                .await
+        });
 
-        axum::serve(listener, router)
-            .with_graceful_shutdown(async move {
-                shutdown_notify.notified().await;
-            })
-            .await?;
+        let result: Result<Session, Box<dyn Error>> = tokio::select! {
+            result = result_rx.recv() => match result {
+                Some(result) => result.map_err(|err| PyriteError::Auth(err).into()),
+                None => Err(PyriteError::Auth("The login server stopped unexpectedly".to_owned()).into()),
+            },
+            _ = tokio::time::sleep(timeout) => Err(PyriteError::Auth(format!(
+                "Login timed out after {} seconds",
+                timeout.as_secs()
+            ))
+            .into()),
+            _ = tokio::signal::ctrl_c() => Err(PyriteError::Auth("Login cancelled".to_owned()).into()),
+        };
 
-        Ok(())
+        // Trigger the graceful shutdown, in-flight responses are still sent
+        shutdown_notify.notify_one();
+        server.await??;
+
+        result
     }
 
     pub async fn read_session() -> Result<Option<Session>, Box<dyn Error>> {
-        let path_buf = Self::get_session_path();
+        Self::read_profile_session(Self::get_profile())
+    }
+
+    pub fn read_profile_session(profile: &str) -> Result<Option<Session>, Box<dyn Error>> {
+        Self::migrate_legacy_session()?;
+
+        let path_buf = Self::get_profile_session_path(profile);
 
         if path_buf.exists() {
             let session = fs::read_to_string(path_buf)?;
@@ -151,10 +461,26 @@ impl AuthService {
         }
     }
 
+    // Written to a temporary file first, so readers never see a partial session
     pub fn write_session(session: &Session) -> Result<(), Box<dyn Error>> {
         let path_buf = Self::get_session_path();
         fs::create_dir_all(path_buf.parent().unwrap())?;
-        fs::write(&path_buf, serde_json::to_string_pretty(session)?)?;
+
+        let tmp_path_buf = path_buf.with_extension(format!("json.{}.tmp", std::process::id()));
+
+        let mut options = OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&tmp_path_buf)?;
+        file.write_all(serde_json::to_string_pretty(session)?.as_bytes())?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path_buf, &path_buf)?;
         Ok(())
     }
 
@@ -165,9 +491,170 @@ impl AuthService {
     }
 
     pub fn get_session_path() -> PathBuf {
+        Self::get_profile_session_path(Self::get_profile())
+    }
+
+    pub fn get_profile_session_path(profile: &str) -> PathBuf {
+        Self::get_profile_dir(profile).join("session.json")
+    }
+
+    pub fn get_pyrite_dir() -> PathBuf {
         dirs::home_dir()
             .expect("Failed to get home directory")
             .join(".pyrite")
-            .join("session.json")
+    }
+
+    pub fn get_profiles_dir() -> PathBuf {
+        Self::get_pyrite_dir().join("profiles")
+    }
+
+    pub fn get_profile_dir(profile: &str) -> PathBuf {
+        Self::get_profiles_dir().join(profile)
+    }
+
+    // Selects the profile from `--profile` or `PYRITE_PROFILE`, falling back to the one
+    // saved by `pyrite profile use`
+    pub fn set_profile(profile: Option<String>) -> Result<(), Box<dyn Error>> {
+        let profile = match profile {
+            Some(profile) => profile,
+            None => Self::read_default_profile()?,
+        };
+        Self::validate_profile_name(&profile)?;
+
+        PROFILE.get_or_init(|| profile);
+        Ok(())
+    }
+
+    pub fn get_profile() -> &'static str {
+        PROFILE.get_or_init(|| DEFAULT_PROFILE.to_owned())
+    }
+
+    pub fn read_default_profile() -> Result<String, Box<dyn Error>> {
+        let path_buf = Self::get_default_profile_path();
+
+        if path_buf.exists() {
+            let profile = fs::read_to_string(path_buf)?.trim().to_owned();
+            if !profile.is_empty() {
+                return Ok(profile);
+            }
+        }
+
+        Ok(DEFAULT_PROFILE.to_owned())
+    }
+
+    pub fn write_default_profile(profile: &str) -> Result<(), Box<dyn Error>> {
+        Self::validate_profile_name(profile)?;
+
+        let path_buf = Self::get_default_profile_path();
+        fs::create_dir_all(path_buf.parent().unwrap())?;
+        fs::write(path_buf, profile)?;
+        Ok(())
+    }
+
+    pub fn delete_default_profile() -> Result<(), Box<dyn Error>> {
+        let path_buf = Self::get_default_profile_path();
+        if path_buf.exists() {
+            fs::remove_file(path_buf)?;
+        }
+        Ok(())
+    }
+
+    pub fn list_profiles() -> Result<Vec<String>, Box<dyn Error>> {
+        Self::migrate_legacy_session()?;
+
+        let path_buf = Self::get_profiles_dir();
+        if !path_buf.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut profiles = fs::read_dir(path_buf)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        profiles.sort();
+
+        Ok(profiles)
+    }
+
+    // Returns false if the profile doesn't exist
+    pub fn delete_profile(profile: &str) -> Result<bool, Box<dyn Error>> {
+        Self::validate_profile_name(profile)?;
+
+        let path_buf = Self::get_profile_dir(profile);
+        if !path_buf.exists() {
+            return Ok(false);
+        }
+
+        fs::remove_dir_all(path_buf)?;
+        Ok(true)
+    }
+
+    // Profile names are used as directory names
+    pub fn validate_profile_name(profile: &str) -> Result<(), Box<dyn Error>> {
+        let is_valid = !profile.is_empty()
+            && profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !is_valid {
+            return Err(PyriteError::Validation(format!(
+                "Invalid profile name {:?}, use letters, numbers, - and _",
+                profile
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn get_default_profile_path() -> PathBuf {
+        Self::get_pyrite_dir().join("profile")
+    }
+
+    // Sessions from before profiles were stored at `~/.pyrite/session.json`
+    fn migrate_legacy_session() -> Result<(), Box<dyn Error>> {
+        let legacy_path = Self::get_pyrite_dir().join("session.json");
+        let default_path = Self::get_profile_session_path(DEFAULT_PROFILE);
+
+        if legacy_path.exists() && !default_path.exists() {
+            fs::create_dir_all(default_path.parent().unwrap())?;
+            fs::rename(legacy_path, default_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_token(claims: serde_json::Value) -> String {
+        format!(
+            "{}.{}.signature",
+            BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256"}"#),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    #[test]
+    fn reads_token_expiry() {
+        let token = get_token(json!({ "sub": "user", "exp": 1_900_000_000 }));
+        assert_eq!(AuthService::get_token_expiry(&token), Some(1_900_000_000));
+
+        let token = get_token(json!({ "sub": "user" }));
+        assert_eq!(AuthService::get_token_expiry(&token), None);
+        assert_eq!(AuthService::get_token_expiry("not-a-jwt"), None);
+    }
+
+    #[test]
+    fn tokens_without_expiry_never_expire() {
+        let session = Session {
+            expires_at: NO_EXPIRY,
+            ..Default::default()
+        };
+        assert!(!AuthService::is_expired(&session));
+        assert!(!AuthService::needs_refresh(&session));
     }
 }
@@ -0,0 +1,191 @@
+use std::{
+    collections::hash_map::RandomState,
+    error::Error,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use tonic::{
+    Code, ConnectError, Request, Status,
+    metadata::{MetadataKey, MetadataValue},
+    service::{Interceptor, interceptor::InterceptedService},
+    transport::{Channel, Endpoint},
+};
+
+use super::{AuthService, EndpointsService};
+
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+pub(crate) type AuthChannel = InterceptedService<Channel, AuthInterceptor>;
+
+// Shared by every service client, so a command connects once
+static CHANNEL: OnceLock<Channel> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub(crate) struct ChannelService;
+
+impl ChannelService {
+    // The channel connects on the first request, not when it is built
+    pub fn get_channel() -> Result<Channel, Box<dyn Error>> {
+        if let Some(channel) = CHANNEL.get() {
+            return Ok(channel.clone());
+        }
+
+        let channel = Endpoint::new(EndpointsService::get_api_url())?
+            .connect_timeout(EndpointsService::get_connect_timeout())
+            .timeout(EndpointsService::get_request_timeout())
+            .tcp_keepalive(Some(Duration::from_secs(30)))
+            .connect_lazy();
+
+        Ok(CHANNEL.get_or_init(|| channel).clone())
+    }
+
+    // Returns a client that attaches the auth cookie to every request
+    pub async fn get_channel_with_auth() -> Result<AuthChannel, Box<dyn Error>> {
+        AuthService::get_cached_session().await?;
+        Ok(InterceptedService::new(
+            Self::get_channel()?,
+            AuthInterceptor,
+        ))
+    }
+
+    // Retries transient failures with jittered exponential backoff, and refreshes the session
+    // once if it is rejected. `fun` is called once per attempt, as requests can't be reused.
+    pub async fn with_retry<T, F, Fut>(fun: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        Self::retry(fun, Self::is_retryable).await
+    }
+
+    // For RPCs that aren't idempotent, e.g. upserts that start a deployment. A request that
+    // timed out may have been processed, so only requests that never left the client are retried.
+    pub async fn with_retry_unsent<T, F, Fut>(fun: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        Self::retry(fun, Self::is_connect_error).await
+    }
+
+    async fn retry<T, F, Fut>(mut fun: F, is_retryable: fn(&Status) -> bool) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let retries = EndpointsService::get_retries();
+        let mut attempt = 0;
+        let mut refreshed = false;
+
+        loop {
+            match fun().await {
+                Err(status) if attempt < retries && is_retryable(&status) => {
+                    tokio::time::sleep(Self::get_backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(status) if status.code() == Code::Unauthenticated && !refreshed => {
+                    if AuthService::refresh_cached_session().await.is_err() {
+                        return Err(status);
+                    }
+                    refreshed = true;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn is_retryable(status: &Status) -> bool {
+        matches!(status.code(), Code::Unavailable | Code::DeadlineExceeded)
+    }
+
+    // The connection couldn't be made, so the request wasn't sent
+    fn is_connect_error(status: &Status) -> bool {
+        if status.code() != Code::Unavailable {
+            return false;
+        }
+
+        let mut source = status.source();
+        while let Some(err) = source {
+            if err.is::<ConnectError>() {
+                return true;
+            }
+            source = err.source();
+        }
+
+        false
+    }
+
+    // Full jitter, a random delay up to the exponential backoff
+    fn get_backoff(attempt: u32) -> Duration {
+        let backoff = RETRY_BASE_DELAY
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(RETRY_MAX_DELAY);
+
+        let random = RandomState::new().build_hasher().finish();
+        Duration::from_millis(random % (backoff.as_millis() as u64 + 1))
+    }
+}
+
+// Reads the session cached by `AuthService::get_cached_session`, so a refreshed session is
+// picked up by clients created before the refresh
+#[derive(Debug, Clone)]
+pub(crate) struct AuthInterceptor;
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut req: Request<()>) -> Result<Request<()>, Status> {
+        let header = AuthService::get_cached_auth_header()
+            .map_err(|err| Status::unauthenticated(err.to_string()))?;
+
+        if let Some((key, value)) = header {
+            let key = MetadataKey::from_bytes(key.as_bytes())
+                .map_err(|err| Status::internal(err.to_string()))?;
+            let value =
+                MetadataValue::try_from(value).map_err(|err| Status::internal(err.to_string()))?;
+            req.metadata_mut().insert(key, value);
+        }
+
+        Ok(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_capped() {
+        for attempt in 0..40 {
+            let max = RETRY_BASE_DELAY
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(RETRY_MAX_DELAY);
+            assert!(ChannelService::get_backoff(attempt) <= max);
+        }
+        assert!(ChannelService::get_backoff(u32::MAX) <= RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn only_connect_errors_are_unsent() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        let status = Status::from_error(Box::new(ConnectError(Box::new(refused))));
+        assert_eq!(status.code(), Code::Unavailable);
+        assert!(ChannelService::is_connect_error(&status));
+
+        assert!(!ChannelService::is_connect_error(&Status::unavailable(
+            "overloaded"
+        )));
+        assert!(!ChannelService::is_connect_error(
+            &Status::deadline_exceeded("timeout")
+        ));
+    }
+
+    #[test]
+    fn reads_retry_timeouts() {
+        assert!(ChannelService::is_retryable(&Status::deadline_exceeded("")));
+        assert!(ChannelService::is_retryable(&Status::unavailable("")));
+        assert!(!ChannelService::is_retryable(&Status::not_found("")));
+    }
+}
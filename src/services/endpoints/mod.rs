@@ -0,0 +1,134 @@
+use std::{error::Error, fs, sync::OnceLock, time::Duration};
+
+use reqwest::Url;
+use rust_dotenv::dotenv::DotEnv;
+
+use crate::{
+    models::endpoints::EndpointsConfig,
+    utils::{PYRITE_API_BASE_URL, WORKFLOWS_BASE_URL, diagnostic::TomlDiagnostic},
+};
+
+use super::AuthService;
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_RETRIES: u32 = 3;
+
+// The endpoints selected for this process, see `EndpointsService::set_endpoints`
+static ENDPOINTS: OnceLock<EndpointsConfig> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub(crate) struct EndpointsService;
+
+impl EndpointsService {
+    // CLI flags and env vars take precedence over `~/.pyrite/config.toml`
+    pub fn set_endpoints(overrides: EndpointsConfig) -> Result<(), Box<dyn Error>> {
+        let endpoints = overrides.merge(Self::read_config_file()?);
+
+        ENDPOINTS.get_or_init(|| endpoints);
+        Ok(())
+    }
+
+    fn get_endpoints() -> &'static EndpointsConfig {
+        ENDPOINTS.get_or_init(EndpointsConfig::default)
+    }
+
+    pub fn get_api_url() -> String {
+        Self::get_endpoints()
+            .api_url
+            .to_owned()
+            .unwrap_or(PYRITE_API_BASE_URL.to_owned())
+    }
+
+    pub fn get_workflows_url() -> String {
+        Self::get_endpoints()
+            .workflows_url
+            .to_owned()
+            .unwrap_or(WORKFLOWS_BASE_URL.to_owned())
+            .trim_end_matches('/')
+            .to_owned()
+    }
+
+    pub fn get_connect_timeout() -> Duration {
+        Duration::from_secs(
+            Self::get_endpoints()
+                .connect_timeout
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        )
+    }
+
+    pub fn get_request_timeout() -> Duration {
+        Duration::from_secs(
+            Self::get_endpoints()
+                .request_timeout
+                .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS),
+        )
+    }
+
+    pub fn get_retries() -> u32 {
+        Self::get_endpoints().retries.unwrap_or(DEFAULT_RETRIES)
+    }
+
+    // Returns the Supabase project URL and API key
+    pub fn get_supabase() -> (String, String) {
+        let endpoints = Self::get_endpoints();
+
+        // Configured values, then compile-time values (set by CI)
+        let project_url = endpoints
+            .supabase_url
+            .to_owned()
+            .or(option_env!("SUPABASE_URL").map(str::to_owned));
+        let api_key = endpoints
+            .supabase_key
+            .to_owned()
+            .or(option_env!("SUPABASE_API_KEY").map(str::to_owned));
+
+        if let (Some(project_url), Some(api_key)) = (&project_url, &api_key) {
+            return (project_url.to_owned(), api_key.to_owned());
+        }
+
+        // Fallback to dotenv in local dev
+        let dot_env = DotEnv::new(if cfg!(debug_assertions) {
+            "local"
+        } else {
+            "prod"
+        });
+
+        (
+            project_url.unwrap_or_else(|| dot_env.get_var("SUPABASE_URL".to_owned()).unwrap()),
+            api_key.unwrap_or_else(|| dot_env.get_var("SUPABASE_API_KEY".to_owned()).unwrap()),
+        )
+    }
+
+    // Supabase names the auth cookie after the project ref, the first label of the host,
+    // e.g. `sb-abcdef-auth-token` for `https://abcdef.supabase.co`
+    pub fn get_cookie_key() -> Result<String, Box<dyn Error>> {
+        let (project_url, _) = Self::get_supabase();
+
+        let url = Url::parse(&project_url)
+            .map_err(|err| format!("Invalid Supabase URL {}: {}", project_url, err))?;
+        let project_ref = url
+            .host_str()
+            .and_then(|host| host.split('.').next())
+            .ok_or(format!(
+                "Invalid Supabase URL {}: missing host",
+                project_url
+            ))?;
+
+        Ok(format!("sb-{}-auth-token", project_ref))
+    }
+
+    fn read_config_file() -> Result<EndpointsConfig, Box<dyn Error>> {
+        let path_buf = AuthService::get_pyrite_dir().join("config.toml");
+
+        if !path_buf.exists() {
+            return Ok(EndpointsConfig::default());
+        }
+
+        let source = fs::read_to_string(&path_buf)?;
+        let config = toml::from_str(&source)
+            .map_err(|err| TomlDiagnostic::new(&path_buf.to_string_lossy(), &source, &err))?;
+
+        Ok(config)
+    }
+}
@@ -1,5 +1,10 @@
 pub mod auth;
+pub mod channel;
+pub mod endpoints;
+pub mod output;
+pub mod plan;
 pub mod projects;
+pub mod pyrite_toml;
 pub mod service_environments;
 #[allow(clippy::module_inception)]
 pub mod services;
@@ -7,7 +12,12 @@ pub mod teams;
 pub mod utils;
 
 pub(crate) use auth::*;
+pub(crate) use channel::*;
+pub(crate) use endpoints::*;
+pub(crate) use output::*;
+pub(crate) use plan::*;
 pub(crate) use projects::*;
+pub(crate) use pyrite_toml::*;
 pub(crate) use services::*;
 pub(crate) use teams::*;
 pub(crate) use utils::*;
@@ -0,0 +1,220 @@
+use std::{error::Error, io, sync::OnceLock};
+
+use comfy_table::{Cell, Table, modifiers, presets};
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use crate::{
+    models::output::OutputFormat,
+    utils::{error::PyriteError, handlebars::setup_handlebars},
+};
+
+// The format selected for this process, see `OutputService::set_output`
+static OUTPUT: OnceLock<OutputFormat> = OnceLock::new();
+
+// Handlebars template from `--format`, takes precedence over `OUTPUT`
+static FORMAT: OnceLock<Option<String>> = OnceLock::new();
+
+// Columns of a resource in table, wide and csv output, json and yaml use its serde form
+pub(crate) trait OutputRow: Serialize {
+    fn get_header(wide: bool) -> Vec<&'static str>;
+
+    fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn Error>>;
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct OutputService;
+
+impl OutputService {
+    pub fn set_output(output: OutputFormat) {
+        OUTPUT.get_or_init(|| output);
+    }
+
+    pub fn get_output() -> OutputFormat {
+        *OUTPUT.get_or_init(OutputFormat::default)
+    }
+
+    pub fn set_format(format: Option<String>) {
+        FORMAT.get_or_init(|| format);
+    }
+
+    fn get_format() -> Option<&'static str> {
+        FORMAT.get().and_then(|format| format.as_deref())
+    }
+
+    pub fn print_list<T: OutputRow>(
+        items: &[T],
+        empty_message: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        // Scripts get an empty list instead of a message
+        let is_table = matches!(Self::get_output(), OutputFormat::Table | OutputFormat::Wide);
+        if items.is_empty() && is_table && Self::get_format().is_none() {
+            cliclack::outro(empty_message)?;
+            return Ok(());
+        }
+
+        Self::print(&items, items)
+    }
+
+    pub fn print_item<T: OutputRow>(item: &T) -> Result<(), Box<dyn Error>> {
+        Self::print(item, std::slice::from_ref(item))
+    }
+
+    fn print<T: OutputRow>(value: &impl Serialize, rows: &[T]) -> Result<(), Box<dyn Error>> {
+        if let Some(template) = Self::get_format() {
+            return Self::write_template(template, rows, io::stdout());
+        }
+
+        match Self::get_output() {
+            OutputFormat::Table => println!("{}", Self::get_table(rows, false)?),
+            OutputFormat::Wide => println!("{}", Self::get_table(rows, true)?),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_norway::to_string(value)?),
+            OutputFormat::Csv => Self::write_csv(rows, io::stdout())?,
+        }
+        Ok(())
+    }
+
+    fn get_table<T: OutputRow>(rows: &[T], wide: bool) -> Result<Table, Box<dyn Error>> {
+        let mut table = Table::new();
+
+        table
+            .load_preset(presets::UTF8_FULL)
+            .apply_modifier(modifiers::UTF8_ROUND_CORNERS)
+            .set_header(T::get_header(wide));
+
+        for row in rows {
+            table.add_row(row.get_row(wide)?);
+        }
+
+        Ok(table)
+    }
+
+    // Renders the template once per item, against its json form. Missing fields are errors, so a
+    // typo doesn't print empty lines.
+    fn write_template<T: OutputRow>(
+        template: &str,
+        rows: &[T],
+        mut writer: impl io::Write,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut handlebars = Handlebars::new();
+        setup_handlebars(&mut handlebars);
+        handlebars.set_strict_mode(true);
+        // The output is plain text, not HTML
+        handlebars.register_escape_fn(handlebars::no_escape);
+
+        // Shells pass `\t` and `\n` through literally, see the `--format` help
+        let template = template.replace("\\t", "\t").replace("\\n", "\n");
+        handlebars
+            .register_template_string("format", template)
+            .map_err(|err| {
+                PyriteError::Validation(format!("Invalid --format template: {}", err))
+            })?;
+
+        for row in rows {
+            let line = handlebars.render("format", row).map_err(|err| {
+                PyriteError::Validation(format!("Invalid --format template: {}", err))
+            })?;
+            writeln!(writer, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    // Always has the wide columns, spreadsheets have room for them
+    fn write_csv<T: OutputRow>(rows: &[T], writer: impl io::Write) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::Writer::from_writer(writer);
+
+        writer.write_record(T::get_header(true))?;
+        for row in rows {
+            writer.write_record(row.get_row(true)?.iter().map(|cell| cell.content()))?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TestRow {
+        id: &'static str,
+        display_name: &'static str,
+    }
+
+    impl OutputRow for TestRow {
+        fn get_header(wide: bool) -> Vec<&'static str> {
+            let mut header = vec!["Id"];
+            if wide {
+                header.push("Name");
+            }
+            header
+        }
+
+        fn get_row(&self, wide: bool) -> Result<Vec<Cell>, Box<dyn Error>> {
+            let mut row = vec![Cell::new(self.id)];
+            if wide {
+                row.push(Cell::new(self.display_name));
+            }
+            Ok(row)
+        }
+    }
+
+    const ROWS: [TestRow; 2] = [
+        TestRow {
+            id: "1",
+            display_name: "a & b",
+        },
+        TestRow {
+            id: "2",
+            display_name: "c",
+        },
+    ];
+
+    fn write_template(template: &str) -> Result<String, Box<dyn Error>> {
+        let mut output = Vec::new();
+        OutputService::write_template(template, &ROWS, &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn writes_templates_per_row() {
+        assert_eq!(
+            write_template("{{id}}\\t{{displayName}}").unwrap(),
+            "1\ta & b\n2\tc\n"
+        );
+        assert_eq!(
+            write_template("{{#if (isEqual id \"2\")}}{{displayName}}{{/if}}").unwrap(),
+            "\nc\n"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_fields_and_invalid_templates() {
+        let err = write_template("{{id}} {{name}}").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PyriteError>(),
+            Some(PyriteError::Validation(_))
+        ));
+
+        let err = write_template("{{#if id}}").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PyriteError>(),
+            Some(PyriteError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn writes_csv_with_wide_columns() {
+        let mut output = Vec::new();
+        OutputService::write_csv(&ROWS, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Id,Name\n1,a & b\n2,c\n"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use std::error::Error;
+
+use pyrite_client_rs::pyrite::v1::services::v1::{
+    UpsertServiceDto,
+    common::v1::{Service, ServiceEnvironment, service_environment::ActiveDeployment},
+    deployments::v1::{DeploymentPortDto, DeploymentRegionDto, DeploymentVolumeDto},
+    upsert_service_dto::DeploymentConfig,
+};
+use serde::Serialize;
+
+use crate::models::plan::{FieldChange, PlanAction, ServicePlan};
+
+use super::{UtilsService, service_environments::ServiceEnvironmentsService};
+
+// The fields of a deployment compared by the plan, shared by the desired config and the live
+// deployment so both sides are read from their typed fields
+#[derive(Debug, Default)]
+struct PlanFields<'a> {
+    image: Option<&'a str>,
+    version: Option<&'a str>,
+    plan: Option<&'a str>,
+    storage: Option<i32>,
+    ports: Option<&'a [DeploymentPortDto]>,
+    volumes: Option<&'a [DeploymentVolumeDto]>,
+    regions: Option<&'a [DeploymentRegionDto]>,
+    env: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PlanService;
+
+impl PlanService {
+    pub async fn get_service_plan(
+        upsert_service_dto: &UpsertServiceDto,
+        services: &[Service],
+    ) -> Result<ServicePlan, Box<dyn Error>> {
+        let environment = upsert_service_dto
+            .environment
+            .to_owned()
+            .unwrap_or_default();
+        let desired = upsert_service_dto
+            .deployment_config
+            .as_ref()
+            .map(Self::get_config_fields)
+            .unwrap_or_default();
+
+        let service_environment =
+            Self::get_live_service_environment(services, &upsert_service_dto.name, &environment)
+                .await?;
+
+        let (action, changes) = match service_environment {
+            Some(service_environment) => {
+                let current = service_environment
+                    .active_deployment
+                    .as_ref()
+                    .map(Self::get_deployment_fields)
+                    .unwrap_or_default();
+                let changes = Self::diff(&current, &desired)?;
+                let action = if changes.is_empty() {
+                    PlanAction::NoOp
+                } else {
+                    PlanAction::Update
+                };
+                (action, changes)
+            }
+            None => (
+                PlanAction::Create,
+                Self::diff(&PlanFields::default(), &desired)?,
+            ),
+        };
+
+        Ok(ServicePlan {
+            name: upsert_service_dto.name.to_owned(),
+            environment,
+            action,
+            changes,
+        })
+    }
+
+    // pyrite.toml has no ids, `services` resolves the name to the service to fetch
+    pub async fn get_live_service_environment(
+        services: &[Service],
+        name: &str,
+        environment: &str,
+    ) -> Result<Option<ServiceEnvironment>, Box<dyn Error>> {
+        let Some(service) = services.iter().find(|service| service.name == name) else {
+            return Ok(None);
+        };
+
+        let service_environment =
+            ServiceEnvironmentsService::list_service_environments(service.id.to_owned())
+                .await?
+                .service_environments
+                .into_iter()
+                .find(|service_environment| service_environment.name == environment);
+
+        match service_environment {
+            Some(service_environment) => Ok(Some(
+                ServiceEnvironmentsService::get_service_environment(service_environment.id).await?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_config_fields(deployment_config: &DeploymentConfig) -> PlanFields<'_> {
+        match deployment_config {
+            DeploymentConfig::DockerConfig(config) => PlanFields {
+                image: Some(&config.image),
+                plan: Some(&config.plan),
+                ports: config.ports_list.as_ref().map(|list| list.ports.as_slice()),
+                volumes: config
+                    .volumes_list
+                    .as_ref()
+                    .map(|list| list.volumes.as_slice()),
+                regions: config
+                    .regions_list
+                    .as_ref()
+                    .map(|list| list.regions.as_slice()),
+                env: config.env.as_deref(),
+                ..Default::default()
+            },
+            DeploymentConfig::PostgresConfig(config) => PlanFields {
+                version: Some(&config.version),
+                plan: Some(&config.plan),
+                storage: config.storage,
+                regions: config
+                    .regions_list
+                    .as_ref()
+                    .map(|list| list.regions.as_slice()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn get_deployment_fields(active_deployment: &ActiveDeployment) -> PlanFields<'_> {
+        match active_deployment {
+            ActiveDeployment::DockerDeployment(deployment) => PlanFields {
+                image: Some(&deployment.image),
+                plan: Some(&deployment.plan),
+                ports: deployment
+                    .ports_list
+                    .as_ref()
+                    .map(|list| list.ports.as_slice()),
+                volumes: deployment
+                    .volumes_list
+                    .as_ref()
+                    .map(|list| list.volumes.as_slice()),
+                regions: deployment
+                    .regions_list
+                    .as_ref()
+                    .map(|list| list.regions.as_slice()),
+                env: deployment.env.as_deref(),
+                ..Default::default()
+            },
+            ActiveDeployment::PostgresDeployment(deployment) => PlanFields {
+                version: Some(&deployment.version),
+                plan: Some(&deployment.plan),
+                storage: deployment.storage,
+                regions: deployment
+                    .regions_list
+                    .as_ref()
+                    .map(|list| list.regions.as_slice()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn diff(
+        current: &PlanFields,
+        desired: &PlanFields,
+    ) -> Result<Vec<FieldChange>, Box<dyn Error>> {
+        let fields = [
+            (
+                "image",
+                Self::render_field(current.image),
+                Self::render_field(desired.image),
+            ),
+            (
+                "version",
+                Self::render_field(current.version),
+                Self::render_field(desired.version),
+            ),
+            (
+                "plan",
+                Self::render_field(current.plan),
+                Self::render_field(desired.plan),
+            ),
+            (
+                "storage",
+                Self::render_field(current.storage),
+                Self::render_field(desired.storage),
+            ),
+            (
+                "ports",
+                Self::render_list(current.ports)?,
+                Self::render_list(desired.ports)?,
+            ),
+            (
+                "volumes",
+                Self::render_list(current.volumes)?,
+                Self::render_list(desired.volumes)?,
+            ),
+            (
+                "regions",
+                Self::render_list(current.regions)?,
+                Self::render_list(desired.regions)?,
+            ),
+        ];
+
+        let mut changes = fields
+            .into_iter()
+            .filter(|(_, current, desired)| current != desired)
+            .map(|(field, current, desired)| FieldChange {
+                field: field.to_owned(),
+                current,
+                desired,
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(change) = Self::diff_env(current.env, desired.env)? {
+            changes.push(change);
+        }
+
+        Ok(changes)
+    }
+
+    // Only env keys are shown, values may contain secrets
+    fn diff_env(
+        current: Option<&str>,
+        desired: Option<&str>,
+    ) -> Result<Option<FieldChange>, Box<dyn Error>> {
+        let current_env = UtilsService::decode_env(current)?;
+        let desired_env = UtilsService::decode_env(desired)?;
+
+        let mut lines = Vec::new();
+        for (key, value) in &desired_env {
+            match current_env.get(key) {
+                None => lines.push(format!("+ {key}")),
+                Some(current_value) if current_value != value => lines.push(format!("~ {key}")),
+                Some(_) => {}
+            }
+        }
+        for key in current_env.keys() {
+            if !desired_env.contains_key(key) {
+                lines.push(format!("- {key}"));
+            }
+        }
+
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let current_keys = current_env.keys().cloned().collect::<Vec<_>>();
+        Ok(Some(FieldChange {
+            field: "env".to_owned(),
+            current: if current_keys.is_empty() {
+                "-".to_owned()
+            } else {
+                current_keys.join("\n")
+            },
+            desired: lines.join("\n"),
+        }))
+    }
+
+    fn render_field(value: Option<impl ToString>) -> String {
+        value
+            .map(|value| value.to_string())
+            .filter(|value| !value.is_empty())
+            .unwrap_or("-".to_owned())
+    }
+
+    // Items are sorted, the API doesn't keep the order of lists
+    fn render_list<T: Serialize>(items: Option<&[T]>) -> Result<String, Box<dyn Error>> {
+        let mut items = items
+            .unwrap_or_default()
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+        items.sort();
+
+        if items.is_empty() {
+            Ok("-".to_owned())
+        } else {
+            Ok(items.join("\n"))
+        }
+    }
+}
@@ -1,57 +1,52 @@
-use pyrite_client_rs::{
-    helpers::request::ReqWithMetadata,
-    pyrite::v1::projects::v1::{
-        Project, ProjectById, Projects, ProjectsByTeamId,
-        project_service_client::ProjectServiceClient,
-    },
+use pyrite_client_rs::pyrite::v1::projects::v1::{
+    Project, ProjectById, Projects, ProjectsByTeamId, project_service_client::ProjectServiceClient,
 };
-use tonic::{Request, transport::channel::Channel};
 
-use crate::utils::PYRITE_API_BASE_URL;
+use crate::utils::error::PyriteError;
 
-use super::AuthService;
+use super::{AuthChannel, ChannelService};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ProjectsService;
 
 impl ProjectsService {
     pub async fn get_projects_client()
-    -> Result<ProjectServiceClient<Channel>, Box<dyn std::error::Error>> {
-        let client = ProjectServiceClient::connect(PYRITE_API_BASE_URL).await?;
+    -> Result<ProjectServiceClient<AuthChannel>, Box<dyn std::error::Error>> {
+        let client = ProjectServiceClient::new(ChannelService::get_channel_with_auth().await?);
         Ok(client)
     }
 
     pub async fn list_projects(
         team_id: Option<String>,
     ) -> Result<Projects, Box<dyn std::error::Error>> {
-        let mut client = Self::get_projects_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-        let req: Request<ProjectsByTeamId> =
-            ReqWithMetadata::with_metadata(ProjectsByTeamId { team_id }, metadata);
-
-        client
-            .find_all_projects(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+        let client = Self::get_projects_client().await?;
+
+        ChannelService::with_retry(|| {
+            let mut client = client.clone();
+            let req = ProjectsByTeamId {
+                team_id: team_id.to_owned(),
+            };
+            async move { client.find_all_projects(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 
     pub async fn get_project(project_id: String) -> Result<Project, Box<dyn std::error::Error>> {
-        let mut client = Self::get_projects_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-        let req: Request<ProjectById> = ReqWithMetadata::with_metadata(
-            ProjectById {
-                id: project_id,
+        let client = Self::get_projects_client().await?;
+
+        ChannelService::with_retry(|| {
+            let mut client = client.clone();
+            let req = ProjectById {
+                id: project_id.to_owned(),
                 with_meta: Some(true),
                 with_secrets: None,
-            },
-            metadata,
-        );
-
-        client
-            .find_one_project(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+            };
+            async move { client.find_one_project(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 }
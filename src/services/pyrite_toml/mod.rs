@@ -0,0 +1,1595 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use pbjson_types::Value;
+use pyrite_client_rs::pyrite::v1::services::v1::common::v1::{Service, ServiceEnvironment};
+use rust_dotenv::dotenv::DotEnv;
+use schemars::schema_for;
+use serde::de::DeserializeOwned;
+use serde_path_to_error::Segment;
+use toml::{
+    Spanned, Table,
+    de::{DeTable, DeValue},
+};
+
+use crate::{
+    models::pyrite_toml::{PyriteToml, ServiceType, TomlService},
+    utils::{
+        diagnostic::{TomlDiagnostic, did_you_mean},
+        error::PyriteError,
+        schema::add_environments_schema,
+    },
+};
+
+use super::UtilsService;
+
+// Deployment fields written by `pyrite export`, as (API JSON key, pyrite.toml key)
+type ExportKeys = &'static [(&'static str, &'static str)];
+
+const DOCKER_EXPORT_FIELDS: ExportKeys = &[
+    ("image", "image"),
+    ("plan", "plan"),
+    ("runtime", "runtime"),
+    ("command", "command"),
+    ("registryId", "registry_id"),
+    ("withProjectEnv", "with_project_env"),
+    ("isPrivate", "is_private"),
+    ("isPrivileged", "is_privileged"),
+];
+const DOCKER_EXPORT_LISTS: ExportKeys = &[
+    ("regionsList", "regions"),
+    ("portsList", "ports"),
+    ("healthChecksList", "health_checks"),
+    ("volumesList", "volumes"),
+    ("filesList", "files"),
+];
+const POSTGRES_EXPORT_FIELDS: ExportKeys = &[
+    ("version", "version"),
+    ("plan", "plan"),
+    ("storage", "storage"),
+];
+const POSTGRES_EXPORT_LISTS: ExportKeys = &[("regionsList", "regions")];
+
+#[derive(Debug, Clone)]
+pub(crate) struct PyriteTomlService;
+
+impl PyriteTomlService {
+    pub fn load(file_path: &str, env: Option<&str>) -> Result<PyriteToml, Box<dyn Error>> {
+        let (table, layers) = Self::read_layers(file_path, env)?;
+        Self::check_unknown_keys(&table, &layers)?;
+        let mut pyrite_toml: PyriteToml = Self::deserialize(table, &layers)?;
+
+        let base_dir = Path::new(file_path).parent().unwrap_or(Path::new(""));
+        for service in &mut pyrite_toml.services {
+            let Some(env_file) = &service.env_file else {
+                continue;
+            };
+
+            let env_file_path = base_dir.join(env_file);
+            let env_file_vars = Self::read_env_file(&env_file_path).map_err(|err| {
+                PyriteError::from(err).map_message(|message| {
+                    format!(
+                        "Failed to read env_file {} of {}: {}",
+                        env_file_path.display(),
+                        service.name,
+                        message
+                    )
+                })
+            })?;
+
+            // Explicit env entries take precedence over the env file
+            let env = service.env.get_or_insert_with(HashMap::new);
+            for (key, value) in env_file_vars {
+                env.entry(key).or_insert_with(|| Value::from(value));
+            }
+        }
+
+        Ok(pyrite_toml)
+    }
+
+    // Merges the `[environments.<env>]` table and the `pyrite.<env>.toml` file over the base file
+    pub fn render(file_path: &str, env: Option<&str>) -> Result<Table, Box<dyn Error>> {
+        let (table, layers) = Self::read_layers(file_path, env)?;
+        Self::check_unknown_keys(&table, &layers)?;
+        Ok(table)
+    }
+
+    // Also returns the merged documents, so errors can point at the file that set a value
+    fn read_layers(
+        file_path: &str,
+        env: Option<&str>,
+    ) -> Result<(Table, Vec<TomlLayer>), Box<dyn Error>> {
+        Self::get_layers(
+            Self::read_source(Path::new(file_path), env)?,
+            file_path,
+            env,
+        )
+    }
+
+    fn get_layers(
+        base: TomlSource,
+        file_path: &str,
+        env: Option<&str>,
+    ) -> Result<(Table, Vec<TomlLayer>), Box<dyn Error>> {
+        let mut table: Table = base.parse()?;
+        let environments = table.remove("environments");
+
+        let mut layers = vec![TomlLayer {
+            source: base.clone(),
+            prefix: Vec::new(),
+        }];
+
+        let Some(env) = env else {
+            return Ok((table, layers));
+        };
+
+        let mut overlays = Vec::new();
+        if let Some(overlay) = environments.as_ref().and_then(|envs| envs.get(env)) {
+            let overlay = overlay.as_table().ok_or_else(|| {
+                PyriteError::ConfigParse(format!("[environments.{}] must be a table", env))
+            })?;
+            overlays.push(overlay.to_owned());
+            layers.push(TomlLayer {
+                source: base,
+                prefix: vec![
+                    TomlKey::Key("environments".to_owned()),
+                    TomlKey::Key(env.to_owned()),
+                ],
+            });
+        }
+
+        let overlay_path = Self::get_overlay_path(file_path, env);
+        if overlay_path.exists() {
+            let overlay = Self::read_source(&overlay_path, Some(env))?;
+            overlays.push(overlay.parse()?);
+            layers.push(TomlLayer {
+                source: overlay,
+                prefix: Vec::new(),
+            });
+        }
+
+        if overlays.is_empty() {
+            return Err(PyriteError::ConfigParse(format!(
+                "No overlay found for environment {}, add [environments.{}] to {} or create {}",
+                env,
+                env,
+                file_path,
+                overlay_path.display()
+            ))
+            .into());
+        }
+
+        for overlay in overlays {
+            Self::merge_overlay(&mut table, overlay)?;
+        }
+
+        // Services without an environment deploy to the selected one, including overlay-only ones
+        if let Some(toml::Value::Array(services)) = table.get_mut("services") {
+            for service in services.iter_mut().filter_map(toml::Value::as_table_mut) {
+                service
+                    .entry("environment")
+                    .or_insert_with(|| toml::Value::String(env.to_owned()));
+            }
+        }
+
+        Ok((table, layers))
+    }
+
+    // A type error in the merged document points at the layer that set the value, with
+    // overlays taking precedence over the base file
+    fn deserialize<T: DeserializeOwned>(
+        table: Table,
+        layers: &[TomlLayer],
+    ) -> Result<T, TomlDiagnostic> {
+        if let [layer] = layers {
+            return layer.source.parse();
+        }
+
+        let names = table
+            .get("services")
+            .and_then(toml::Value::as_array)
+            .map(|services| {
+                services
+                    .iter()
+                    .map(|service| service.get("name").and_then(toml::Value::as_str))
+                    .map(|name| name.map(str::to_owned))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let err = match serde_path_to_error::deserialize(toml::Value::Table(table)) {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        // Services are merged by name, so their index in the merged document means nothing in
+        // the layers
+        let mut keys = Vec::new();
+        for segment in err.path() {
+            let key = match segment {
+                Segment::Seq { index } => match (keys.as_slice(), names.get(*index)) {
+                    ([TomlKey::Key(key)], Some(Some(name))) if key == "services" => {
+                        TomlKey::Name(name.to_owned())
+                    }
+                    _ => TomlKey::Index(*index),
+                },
+                Segment::Map { key } => TomlKey::Key(key.to_owned()),
+                Segment::Enum { variant } => TomlKey::Key(variant.to_owned()),
+                Segment::Unknown => break,
+            };
+            keys.push(key);
+        }
+
+        Err(Self::get_layer_diagnostic(
+            layers,
+            &keys,
+            err.inner().message(),
+            err.path(),
+        ))
+    }
+
+    // Points at `keys` in the layer that set the value, `path` names it when no layer has it
+    fn get_layer_diagnostic(
+        layers: &[TomlLayer],
+        keys: &[TomlKey],
+        message: &str,
+        path: impl Display,
+    ) -> TomlDiagnostic {
+        // `max_by_key` keeps the last maximum, so overlays win ties with the base file
+        let location = layers
+            .iter()
+            .map(|layer| {
+                let path = [layer.prefix.as_slice(), keys].concat();
+                let (found, span) = layer.source.find_span(&path);
+                (found.saturating_sub(layer.prefix.len()), layer, span)
+            })
+            .filter(|(found, _, _)| *found > 0)
+            .max_by_key(|(found, _, _)| *found);
+
+        match location {
+            Some((_, layer, span)) => layer.source.get_diagnostic(message, span),
+            None => layers[0]
+                .source
+                .get_diagnostic(&format!("{}: {}", path, message), None),
+        }
+    }
+
+    // Deploy fails on the first unknown key like on a type error, with the same message as
+    // serde's so the diagnostic suggests the closest key
+    fn check_unknown_keys(table: &Table, layers: &[TomlLayer]) -> Result<(), TomlDiagnostic> {
+        let value = toml::Value::Table(table.clone());
+        let schema = Self::get_json_schema();
+
+        let mut unknown_keys = Vec::new();
+        Self::find_unknown_keys(&value, &schema, &schema, "", &[], &mut unknown_keys);
+
+        let Some(unknown_key) = unknown_keys.into_iter().next() else {
+            return Ok(());
+        };
+
+        let candidates = unknown_key
+            .candidates
+            .iter()
+            .map(|candidate| format!("`{}`", candidate))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!(
+            "unknown field `{}`, expected one of {}",
+            unknown_key.key, candidates
+        );
+
+        Err(Self::get_layer_diagnostic(
+            layers,
+            &unknown_key.keys,
+            &message,
+            &unknown_key.path,
+        ))
+    }
+
+    // Reverse of the mapping done by `DeployCommands`, so that deploying the result is a no-op
+    pub fn export_service_environment(
+        service: &Service,
+        service_environment: &ServiceEnvironment,
+        include_secrets: bool,
+    ) -> Result<Option<Table>, Box<dyn Error>> {
+        let deployment =
+            UtilsService::get_oneof_value(serde_json::to_value(service_environment)?, "Deployment");
+        if deployment.is_null() {
+            return Ok(None);
+        }
+
+        let mut table = Table::new();
+        table.insert("name".to_owned(), service.name.to_owned().into());
+        table.insert(
+            "environment".to_owned(),
+            service_environment.name.to_owned().into(),
+        );
+        table.insert("type".to_owned(), service.r#type.to_owned().into());
+
+        let (fields, lists): (ExportKeys, ExportKeys) = match service.r#type.as_str() {
+            "postgres" => (POSTGRES_EXPORT_FIELDS, POSTGRES_EXPORT_LISTS),
+            _ => (DOCKER_EXPORT_FIELDS, DOCKER_EXPORT_LISTS),
+        };
+
+        for (json_key, toml_key) in fields {
+            if let Some(value) = deployment
+                .get(json_key)
+                .cloned()
+                .and_then(Self::to_toml_value)
+            {
+                table.insert(toml_key.to_string(), value);
+            }
+        }
+
+        // Lists are wrapped in a message, e.g. `{ "ports": [...] }`
+        for (json_key, toml_key) in lists {
+            let items = deployment
+                .get(json_key)
+                .and_then(serde_json::Value::as_object)
+                .and_then(|list| list.values().next())
+                .cloned()
+                .and_then(Self::to_toml_value);
+
+            if let Some(items) = items {
+                table.insert(toml_key.to_string(), items);
+            }
+        }
+
+        if let Some(args) = deployment.get("args").and_then(serde_json::Value::as_str) {
+            let args = args
+                .split_whitespace()
+                .map(|arg| toml::Value::String(Self::escape_interpolation(arg)))
+                .collect::<Vec<_>>();
+            table.insert("args".to_owned(), toml::Value::Array(args));
+        }
+
+        let env =
+            UtilsService::decode_env(deployment.get("env").and_then(serde_json::Value::as_str))?;
+        if !env.is_empty() {
+            let env = env
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    // Placeholders are resolved from the environment on deploy
+                    let value = if include_secrets {
+                        Self::to_toml_value(value)?
+                    } else {
+                        toml::Value::String(format!("${{{}}}", key))
+                    };
+                    Some((key, value))
+                })
+                .collect::<Table>();
+            table.insert("env".to_owned(), toml::Value::Table(env));
+        }
+
+        // Make sure the exported service can be loaded again
+        toml::Value::Table(table.clone())
+            .try_into::<TomlService>()
+            .map_err(|err| {
+                format!(
+                    "Failed to export {} ({}): {}",
+                    service.name,
+                    service_environment.name,
+                    err.message()
+                )
+            })?;
+
+        Ok(Some(table))
+    }
+
+    fn to_toml_value(value: serde_json::Value) -> Option<toml::Value> {
+        match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::Bool(value) => Some(toml::Value::Boolean(value)),
+            serde_json::Value::Number(value) => value
+                .as_i64()
+                .map(toml::Value::Integer)
+                .or(value.as_f64().map(toml::Value::Float)),
+            serde_json::Value::String(value) => {
+                Some(toml::Value::String(Self::escape_interpolation(&value)))
+            }
+            serde_json::Value::Array(items) => Some(toml::Value::Array(
+                items.into_iter().filter_map(Self::to_toml_value).collect(),
+            )),
+            // API messages use camelCase keys, pyrite.toml uses snake_case
+            serde_json::Value::Object(map) => Some(toml::Value::Table(
+                map.into_iter()
+                    .filter_map(|(key, value)| {
+                        Self::to_toml_value(value).map(|value| (Self::to_snake_case(&key), value))
+                    })
+                    .collect(),
+            )),
+        }
+    }
+
+    fn to_snake_case(key: &str) -> String {
+        let mut out = String::with_capacity(key.len());
+        for ch in key.chars() {
+            if ch.is_ascii_uppercase() {
+                out.push('_');
+                out.push(ch.to_ascii_lowercase());
+            } else {
+                out.push(ch);
+            }
+        }
+        out
+    }
+
+    fn escape_interpolation(value: &str) -> String {
+        value.replace("${", "$${")
+    }
+
+    pub fn get_json_schema() -> serde_json::Value {
+        let mut schema = schema_for!(PyriteToml);
+        add_environments_schema(&mut schema);
+        schema.to_value()
+    }
+
+    // Collects every problem in the file instead of stopping at the first one
+    pub fn validate(file_path: &str, env: Option<&str>) -> Result<Vec<String>, Box<dyn Error>> {
+        let (table, layers) = Self::read_layers(file_path, env)?;
+        Self::validate_layers(table, &layers)
+    }
+
+    // Validates a document before it is written to `file_path`
+    pub fn validate_source(file_path: &str, raw: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let base = Self::get_source(Path::new(file_path), raw.to_owned(), None)?;
+        let (table, layers) = Self::get_layers(base, file_path, None)?;
+        Self::validate_layers(table, &layers)
+    }
+
+    fn validate_layers(table: Table, layers: &[TomlLayer]) -> Result<Vec<String>, Box<dyn Error>> {
+        let value = toml::Value::Table(table.clone());
+        let schema = Self::get_json_schema();
+
+        let mut unknown_keys = Vec::new();
+        Self::find_unknown_keys(&value, &schema, &schema, "", &[], &mut unknown_keys);
+
+        let mut issues = unknown_keys
+            .into_iter()
+            .map(|unknown_key| {
+                let candidates = unknown_key
+                    .candidates
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                match did_you_mean(&unknown_key.key, &candidates) {
+                    Some(candidate) => format!(
+                        "{}: unknown key, did you mean `{}`?",
+                        unknown_key.path, candidate
+                    ),
+                    None => format!("{}: unknown key", unknown_key.path),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if let Err(diagnostic) = Self::deserialize::<PyriteToml>(table, layers) {
+            issues.push(diagnostic.to_string());
+        }
+
+        let services = value
+            .get("services")
+            .and_then(toml::Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut seen = HashSet::new();
+        for (idx, service) in services.iter().enumerate() {
+            let name = service
+                .get("name")
+                .and_then(toml::Value::as_str)
+                .unwrap_or_default();
+            let environment = service
+                .get("environment")
+                .and_then(toml::Value::as_str)
+                .unwrap_or_default();
+            let path = format!("services[{}]", idx);
+
+            if !seen.insert((name, environment)) {
+                issues.push(format!(
+                    "{}: service {} is declared more than once for environment {}",
+                    path, name, environment
+                ));
+            }
+
+            if let Some(type_name) = service.get("type").and_then(toml::Value::as_str) {
+                match ServiceType::from_name(type_name) {
+                    Ok(service_type) => {
+                        for key in service_type.get_required_keys() {
+                            if service.get(key).is_none() {
+                                issues.push(format!("{}: missing key {}", path, key));
+                            }
+                        }
+                        for key in service_type.get_unsupported_keys() {
+                            if service.get(key).is_some() {
+                                issues.push(format!(
+                                    "{}.{}: not supported by {} services",
+                                    path, key, type_name
+                                ));
+                            }
+                        }
+                    }
+                    Err(err) => issues.push(format!("{}: {}", path, err)),
+                }
+            }
+
+            let ports = Self::get_ports(service, "ports", &path, &mut issues);
+            let health_check_ports = Self::get_ports(service, "health_checks", &path, &mut issues);
+            for (health_check_path, port) in health_check_ports {
+                if !ports.iter().any(|(_, declared)| *declared == port) {
+                    issues.push(format!(
+                        "{}: health check uses port {} which is not declared in ports",
+                        health_check_path, port
+                    ));
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    fn get_ports(
+        service: &toml::Value,
+        key: &str,
+        path: &str,
+        issues: &mut Vec<String>,
+    ) -> Vec<(String, i64)> {
+        let items = service
+            .get(key)
+            .and_then(toml::Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut ports = Vec::new();
+        for (idx, item) in items.iter().enumerate() {
+            let item_path = format!("{}.{}[{}]", path, key, idx);
+            let Some(port) = item.get("port").and_then(toml::Value::as_integer) else {
+                continue;
+            };
+
+            if !(1..=65535).contains(&port) {
+                issues.push(format!(
+                    "{}.port: {} is not a valid port, expected 1-65535",
+                    item_path, port
+                ));
+            }
+
+            ports.push((item_path, port));
+        }
+
+        ports
+    }
+
+    // `keys` is the path with services matched by name, to find the key in its layer
+    fn find_unknown_keys(
+        value: &toml::Value,
+        schema: &serde_json::Value,
+        root: &serde_json::Value,
+        path: &str,
+        keys: &[TomlKey],
+        unknown_keys: &mut Vec<UnknownKey>,
+    ) {
+        let schema = Self::resolve_schema(schema, root);
+
+        match value {
+            toml::Value::Table(table) => {
+                let properties = schema
+                    .get("properties")
+                    .and_then(serde_json::Value::as_object);
+                let additional_properties = schema.get("additionalProperties");
+
+                for (key, value) in table {
+                    let key_path = if path.is_empty() {
+                        key.to_owned()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    let key_keys = [keys, &[TomlKey::Key(key.to_owned())]].concat();
+
+                    match (
+                        properties.and_then(|props| props.get(key)),
+                        additional_properties,
+                    ) {
+                        (Some(property), _) => Self::find_unknown_keys(
+                            value,
+                            property,
+                            root,
+                            &key_path,
+                            &key_keys,
+                            unknown_keys,
+                        ),
+                        (None, Some(serde_json::Value::Bool(false))) => {
+                            unknown_keys.push(UnknownKey {
+                                key: key.to_owned(),
+                                path: key_path,
+                                keys: key_keys,
+                                candidates: properties
+                                    .map(|props| props.keys().cloned().collect())
+                                    .unwrap_or_default(),
+                            })
+                        }
+                        (None, Some(additional_properties)) => Self::find_unknown_keys(
+                            value,
+                            additional_properties,
+                            root,
+                            &key_path,
+                            &key_keys,
+                            unknown_keys,
+                        ),
+                        (None, None) => {}
+                    }
+                }
+            }
+            toml::Value::Array(items) => {
+                if let Some(items_schema) = schema.get("items") {
+                    for (idx, item) in items.iter().enumerate() {
+                        let item_path = format!("{}[{}]", path, idx);
+                        let name = item.get("name").and_then(toml::Value::as_str);
+                        let item_key = match (keys, name) {
+                            ([TomlKey::Key(key)], Some(name)) if key == "services" => {
+                                TomlKey::Name(name.to_owned())
+                            }
+                            _ => TomlKey::Index(idx),
+                        };
+                        Self::find_unknown_keys(
+                            item,
+                            items_schema,
+                            root,
+                            &item_path,
+                            &[keys, &[item_key]].concat(),
+                            unknown_keys,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Follows `$ref` and picks the object variant of `anyOf` used for optional fields
+    fn resolve_schema<'a>(
+        schema: &'a serde_json::Value,
+        root: &'a serde_json::Value,
+    ) -> &'a serde_json::Value {
+        if let Some(reference) = schema.get("$ref").and_then(serde_json::Value::as_str) {
+            let resolved = reference
+                .strip_prefix('#')
+                .and_then(|pointer| root.pointer(pointer));
+            if let Some(resolved) = resolved {
+                return Self::resolve_schema(resolved, root);
+            }
+        }
+
+        if let Some(variants) = schema.get("anyOf").and_then(serde_json::Value::as_array) {
+            let variant = variants.iter().find(|variant| {
+                variant.get("type").and_then(serde_json::Value::as_str) != Some("null")
+            });
+            if let Some(variant) = variant {
+                return Self::resolve_schema(variant, root);
+            }
+        }
+
+        schema
+    }
+
+    fn read_source(path: &Path, env: Option<&str>) -> Result<TomlSource, Box<dyn Error>> {
+        if !path.exists() {
+            return Err(
+                PyriteError::NotFound(format!("File {} does not exist", path.display())).into(),
+            );
+        }
+
+        Self::get_source(path, fs::read_to_string(path)?, env)
+    }
+
+    // Variables only have to be set in the base document and the overlay of `env`
+    fn get_source(
+        path: &Path,
+        raw: String,
+        env: Option<&str>,
+    ) -> Result<TomlSource, Box<dyn Error>> {
+        let map_err = |err: PyriteError| {
+            err.map_message(|message| {
+                format!("Failed to interpolate {}: {}", path.display(), message)
+            })
+        };
+
+        let (interpolated, substitutions, unset) = Self::interpolate(&raw).map_err(map_err)?;
+        let skipped = Self::get_other_overlay_spans(&interpolated, env);
+        let unset = unset
+            .into_iter()
+            .find(|(offset, _)| !skipped.iter().any(|span| span.contains(offset)));
+        if let Some((_, err)) = unset {
+            return Err(map_err(err).into());
+        }
+
+        Ok(TomlSource {
+            path: path.display().to_string(),
+            raw,
+            interpolated,
+            substitutions,
+        })
+    }
+
+    // Spans of the values in `[environments.<name>]` overlays other than `env`, in the
+    // interpolated source. Empty if it can't be parsed, then every variable is needed.
+    fn get_other_overlay_spans(interpolated: &str, env: Option<&str>) -> Vec<Range<usize>> {
+        let Ok(root) = DeTable::parse(interpolated) else {
+            return Vec::new();
+        };
+        let root = root.into_inner();
+        let Some(environments) = root
+            .get("environments")
+            .and_then(|envs| envs.get_ref().as_table())
+        else {
+            return Vec::new();
+        };
+
+        let mut spans = Vec::new();
+        for (name, overlay) in environments {
+            if Some(name.get_ref().as_ref()) != env {
+                Self::get_value_spans(overlay, &mut spans);
+            }
+        }
+        spans
+    }
+
+    // Tables defined by a header don't span their keys, so only values are collected
+    fn get_value_spans(value: &Spanned<DeValue>, spans: &mut Vec<Range<usize>>) {
+        match value.get_ref() {
+            DeValue::Table(table) => {
+                for value in table.values() {
+                    Self::get_value_spans(value, spans);
+                }
+            }
+            DeValue::Array(items) => {
+                for item in items {
+                    Self::get_value_spans(item, spans);
+                }
+            }
+            _ => spans.push(value.span()),
+        }
+    }
+
+    fn get_overlay_path(file_path: &str, env: &str) -> PathBuf {
+        let path = Path::new(file_path);
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or("pyrite".to_owned());
+
+        path.with_file_name(format!("{}.{}.toml", stem, env))
+    }
+
+    fn merge_overlay(table: &mut Table, overlay: Table) -> Result<(), Box<dyn Error>> {
+        for (key, value) in overlay {
+            match value {
+                toml::Value::Array(services) if key == "services" => {
+                    Self::merge_services(table, services)?
+                }
+                value => Self::merge_value(table, key, value, &[]),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Overlay services are matched by name, unknown names are added as new services
+    fn merge_services(
+        table: &mut Table,
+        overlay_services: Vec<toml::Value>,
+    ) -> Result<(), Box<dyn Error>> {
+        let services = table
+            .entry("services")
+            .or_insert(toml::Value::Array(Vec::new()))
+            .as_array_mut()
+            .ok_or_else(|| PyriteError::ConfigParse("services must be an array".to_owned()))?;
+
+        for overlay_service in overlay_services {
+            let toml::Value::Table(mut overlay_service) = overlay_service else {
+                return Err(
+                    PyriteError::ConfigParse("Overlay services must be tables".to_owned()).into(),
+                );
+            };
+
+            let name = overlay_service
+                .get("name")
+                .and_then(toml::Value::as_str)
+                .ok_or_else(|| {
+                    PyriteError::ConfigParse("Overlay services must have a name".to_owned())
+                })?
+                .to_owned();
+
+            // Lists replace the base list unless they are named in `extend`
+            let extend = overlay_service
+                .remove("extend")
+                .and_then(|extend| extend.try_into::<Vec<String>>().ok())
+                .unwrap_or_default();
+
+            let service = services
+                .iter_mut()
+                .filter_map(toml::Value::as_table_mut)
+                .find(|service| service.get("name").and_then(toml::Value::as_str) == Some(&name));
+
+            match service {
+                Some(service) => {
+                    for (key, value) in overlay_service {
+                        Self::merge_value(service, key, value, &extend);
+                    }
+                }
+                None => services.push(toml::Value::Table(overlay_service)),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Tables are merged key by key and other values replace the base value. Lists replace the
+    // base list too, unless their key is in `extend`, then the overlay items are appended.
+    // `extend` only applies to the keys of a service, not to nested tables.
+    fn merge_value(table: &mut Table, key: String, value: toml::Value, extend: &[String]) {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                for (key, value) in overlay {
+                    Self::merge_value(base, key, value, &[]);
+                }
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(overlay))
+                if extend.contains(&key) =>
+            {
+                base.extend(overlay);
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+
+    // Replaces `${VAR}` and `${VAR:-default}` with environment variables, `$${` escapes a literal `${`.
+    // Unset variables are replaced with an empty string and returned with their offset in the
+    // interpolated source, the caller decides whether they are needed.
+    fn interpolate(raw: &str) -> Result<(String, Substitutions, UnsetVariables), PyriteError> {
+        Self::interpolate_with(raw, |name| std::env::var(name).ok())
+    }
+
+    fn interpolate_with(
+        raw: &str,
+        get_var: impl Fn(&str) -> Option<String>,
+    ) -> Result<(String, Substitutions, UnsetVariables), PyriteError> {
+        let mut out = String::with_capacity(raw.len());
+        let mut substitutions = Vec::new();
+        let mut unset = Vec::new();
+        let mut context = TomlContext::Bare;
+        let mut rest = raw;
+
+        while !rest.is_empty() {
+            // Comments are left untouched
+            if context == TomlContext::Comment {
+                let (len, next_context) = context.next(rest);
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+                context = next_context;
+                continue;
+            }
+
+            let offset = raw.len() - rest.len();
+            if rest.starts_with("$${") {
+                substitutions.push((out.len()..out.len() + 2, offset..offset + 3));
+                out.push_str("${");
+                rest = &rest[3..];
+                continue;
+            }
+
+            if !rest.starts_with("${") {
+                let (len, next_context) = context.next(rest);
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+                context = next_context;
+                continue;
+            }
+
+            let line_idx = raw[..offset].matches('\n').count();
+            let line_start = raw[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+            let line = raw[line_start..].lines().next().unwrap_or_default().trim();
+
+            let end = rest.find('}').ok_or(PyriteError::ConfigParse(format!(
+                "Unterminated variable on line {}: {}",
+                line_idx + 1,
+                line
+            )))?;
+            let expr = &rest[2..end];
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+
+            let value = match (get_var(name), default) {
+                (Some(value), Some(default)) if value.is_empty() => default.to_owned(),
+                (Some(value), _) => value,
+                (None, Some(default)) => default.to_owned(),
+                (None, None) => {
+                    let key = line
+                        .split_once('=')
+                        .map(|(key, _)| key.trim())
+                        .unwrap_or_default();
+                    unset.push((
+                        out.len(),
+                        PyriteError::Validation(format!(
+                            "Variable ${{{}}} used by `{}` on line {} is not set",
+                            name,
+                            key,
+                            line_idx + 1
+                        )),
+                    ));
+                    // Keeps the document parseable when the variable is outside a string
+                    if context == TomlContext::Bare {
+                        "\"\"".to_owned()
+                    } else {
+                        String::new()
+                    }
+                }
+            };
+
+            let value = context.escape(&value).ok_or(PyriteError::ConfigParse(format!(
+                "Variable ${{{}}} on line {} can't be written in a literal string, use a basic string",
+                name,
+                line_idx + 1
+            )))?;
+            substitutions.push((out.len()..out.len() + value.len(), offset..offset + end + 1));
+            out.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+
+        Ok((out, substitutions, unset))
+    }
+
+    // Same parser as the dotenv fallback of `EndpointsService`
+    fn read_env_file(path: &Path) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        // `load_env` searches parent directories and returns no vars for a missing file
+        if !path.is_file() {
+            return Err(
+                PyriteError::NotFound(format!("File {} does not exist", path.display())).into(),
+            );
+        }
+
+        Ok(DotEnv::load_env(&path.to_string_lossy())?)
+    }
+}
+
+// (interpolated, raw) byte ranges of each replaced `${VAR}` and `$${`
+type Substitutions = Vec<(Range<usize>, Range<usize>)>;
+
+// Interpolated offset of each unset `${VAR}` and the error to report if it is needed
+type UnsetVariables = Vec<(usize, PyriteError)>;
+
+// A key of the merged document that the schema doesn't allow
+#[derive(Debug)]
+struct UnknownKey {
+    key: String,
+    // e.g. `services[0].healthchecks`
+    path: String,
+    keys: Vec<TomlKey>,
+    candidates: Vec<String>,
+}
+
+// A file as written and after interpolation, diagnostics point into the file as written
+#[derive(Debug, Clone)]
+struct TomlSource {
+    path: String,
+    raw: String,
+    interpolated: String,
+    substitutions: Substitutions,
+}
+
+// A document merged by `PyriteTomlService::render`, at `prefix` in its source file
+#[derive(Debug)]
+struct TomlLayer {
+    source: TomlSource,
+    prefix: Vec<TomlKey>,
+}
+
+#[derive(Debug, Clone)]
+enum TomlKey {
+    Key(String),
+    Index(usize),
+    // An item of an array of tables, matched by its `name`
+    Name(String),
+}
+
+impl TomlSource {
+    fn parse<T: DeserializeOwned>(&self) -> Result<T, TomlDiagnostic> {
+        toml::from_str(&self.interpolated)
+            .map_err(|err| self.get_diagnostic(err.message(), err.span()))
+    }
+
+    // `span` is in the interpolated source
+    fn get_diagnostic(&self, message: &str, span: Option<Range<usize>>) -> TomlDiagnostic {
+        let span = span.map(|span| {
+            self.get_raw_offset(span.start, false)..self.get_raw_offset(span.end, true)
+        });
+        TomlDiagnostic::with_span(&self.path, &self.raw, message, span)
+    }
+
+    // Offsets inside a substituted value map to the start or end of its `${VAR}`
+    fn get_raw_offset(&self, offset: usize, is_end: bool) -> usize {
+        let mut raw_offset = offset;
+        for (interpolated, raw) in &self.substitutions {
+            if offset <= interpolated.start {
+                break;
+            }
+            if offset < interpolated.end {
+                return if is_end { raw.end } else { raw.start };
+            }
+            raw_offset = offset - interpolated.end + raw.end;
+        }
+        raw_offset
+    }
+
+    // Span of the value at `keys`, or of its deepest parent, and how many keys were found
+    fn find_span(&self, keys: &[TomlKey]) -> (usize, Option<Range<usize>>) {
+        let Ok(root) = DeTable::parse(&self.interpolated) else {
+            return (0, None);
+        };
+
+        let root = DeValue::Table(root.into_inner());
+        let mut value = &root;
+        let mut span = None;
+        let mut found = 0;
+
+        for key in keys {
+            let next = match key {
+                TomlKey::Key(key) => value.get(key.as_str()),
+                TomlKey::Index(idx) => value.get(*idx),
+                TomlKey::Name(name) => value.as_array().and_then(|items| {
+                    items.iter().find(|item| {
+                        item.get_ref()
+                            .get("name")
+                            .and_then(|item_name| item_name.get_ref().as_str())
+                            == Some(name)
+                    })
+                }),
+            };
+            let Some(next) = next else {
+                break;
+            };
+
+            span = Some(next.span());
+            value = next.get_ref();
+            found += 1;
+        }
+
+        (found, span)
+    }
+}
+
+// Where a `${VAR}` is in the TOML source, which decides how its value is escaped
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TomlContext {
+    Bare,
+    Comment,
+    BasicString,
+    MultiLineBasicString,
+    LiteralString,
+    MultiLineLiteralString,
+}
+
+impl TomlContext {
+    // Length of the next token in `rest` and the context after it
+    fn next(self, rest: &str) -> (usize, TomlContext) {
+        let char_len = rest.chars().next().map_or(1, char::len_utf8);
+
+        match self {
+            TomlContext::Bare if rest.starts_with(r#"""""#) => {
+                (3, TomlContext::MultiLineBasicString)
+            }
+            TomlContext::Bare if rest.starts_with("'''") => {
+                (3, TomlContext::MultiLineLiteralString)
+            }
+            TomlContext::Bare if rest.starts_with('"') => (1, TomlContext::BasicString),
+            TomlContext::Bare if rest.starts_with('\'') => (1, TomlContext::LiteralString),
+            TomlContext::Bare if rest.starts_with('#') => (1, TomlContext::Comment),
+            TomlContext::Comment if rest.starts_with('\n') => (1, TomlContext::Bare),
+            // Skip escaped characters, so `\"` doesn't end the string
+            TomlContext::BasicString | TomlContext::MultiLineBasicString
+                if rest.starts_with('\\') =>
+            {
+                let escaped_len = rest[1..].chars().next().map_or(0, char::len_utf8);
+                (1 + escaped_len, self)
+            }
+            TomlContext::BasicString if rest.starts_with('"') => (1, TomlContext::Bare),
+            TomlContext::MultiLineBasicString if rest.starts_with(r#"""""#) => {
+                (3, TomlContext::Bare)
+            }
+            TomlContext::LiteralString if rest.starts_with('\'') => (1, TomlContext::Bare),
+            TomlContext::MultiLineLiteralString if rest.starts_with("'''") => {
+                (3, TomlContext::Bare)
+            }
+            _ => (char_len, self),
+        }
+    }
+
+    // Literal strings have no escapes, `None` if the value can't be written in one
+    fn escape(self, value: &str) -> Option<String> {
+        match self {
+            TomlContext::BasicString => Some(
+                value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r")
+                    .replace('\t', "\\t"),
+            ),
+            // Newlines can stay, quotes are escaped so they can't end the string
+            TomlContext::MultiLineBasicString => {
+                Some(value.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            TomlContext::LiteralString if value.contains(['\'', '\n', '\r']) => None,
+            TomlContext::MultiLineLiteralString if value.contains("'''") => None,
+            _ => Some(value.to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static TEST_DIR_ID: AtomicUsize = AtomicUsize::new(0);
+
+    // A temporary directory holding `files`, unique per test as tests run in parallel
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let id = TEST_DIR_ID.fetch_add(1, Ordering::Relaxed);
+            let dir =
+                std::env::temp_dir().join(format!("pyrite-test-{}-{}", std::process::id(), id));
+            fs::create_dir_all(&dir).unwrap();
+            for (name, data) in files {
+                fs::write(dir.join(name), data).unwrap();
+            }
+            Self(dir)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn interpolate(raw: &str) -> Result<String, PyriteError> {
+        let vars = HashMap::from([
+            ("IMAGE", "nginx:latest"),
+            ("EMPTY", ""),
+            ("QUOTED", r#"say "hi" \o/"#),
+            ("MULTI_LINE", "a\nb"),
+            ("APOSTROPHE", "it's"),
+        ]);
+        let (out, _, unset) = PyriteTomlService::interpolate_with(raw, |name| {
+            vars.get(name).map(|value| value.to_string())
+        })?;
+        match unset.into_iter().next() {
+            Some((_, err)) => Err(err),
+            None => Ok(out),
+        }
+    }
+
+    #[test]
+    fn interpolates_variables_and_defaults() {
+        assert_eq!(
+            interpolate(r#"image = "${IMAGE}""#).unwrap(),
+            r#"image = "nginx:latest""#
+        );
+        assert_eq!(
+            interpolate(r#"plan = "${PLAN:-small}""#).unwrap(),
+            r#"plan = "small""#
+        );
+        assert_eq!(
+            interpolate(r#"plan = "${EMPTY:-small}""#).unwrap(),
+            r#"plan = "small""#
+        );
+        assert_eq!(interpolate(r#"plan = "${EMPTY}""#).unwrap(), r#"plan = """#);
+        assert_eq!(
+            interpolate(r#"command = "echo $${IMAGE} $HOME""#).unwrap(),
+            r#"command = "echo ${IMAGE} $HOME""#
+        );
+    }
+
+    #[test]
+    fn escapes_by_string_kind() {
+        assert_eq!(
+            interpolate(r#"a = "${QUOTED} ${MULTI_LINE}""#).unwrap(),
+            r#"a = "say \"hi\" \\o/ a\nb""#
+        );
+        assert_eq!(
+            interpolate(r#"a = '${QUOTED}'"#).unwrap(),
+            r#"a = 'say "hi" \o/'"#
+        );
+        assert_eq!(
+            interpolate("a = \"\"\"${MULTI_LINE} ${QUOTED}\"\"\"").unwrap(),
+            "a = \"\"\"a\nb say \\\"hi\\\" \\\\o/\"\"\""
+        );
+        assert_eq!(
+            interpolate("a = '''${MULTI_LINE} ${APOSTROPHE}'''").unwrap(),
+            "a = '''a\nb it's'''"
+        );
+        // An escaped quote doesn't end the basic string
+        assert_eq!(
+            interpolate(r#"a = "\"${QUOTED}""#).unwrap(),
+            r#"a = "\"say \"hi\" \\o/""#
+        );
+        // Literal strings can't hold quotes or newlines
+        assert!(interpolate("a = '${APOSTROPHE}'").is_err());
+        assert!(interpolate("a = '${MULTI_LINE}'").is_err());
+    }
+
+    #[test]
+    fn skips_comments() {
+        let raw = "# uses ${MISSING}\nimage = \"${IMAGE}\" # ${MISSING}\nplan = \"#${IMAGE}\"\n";
+        assert_eq!(
+            interpolate(raw).unwrap(),
+            "# uses ${MISSING}\nimage = \"nginx:latest\" # ${MISSING}\nplan = \"#nginx:latest\"\n"
+        );
+    }
+
+    #[test]
+    fn reports_unset_variables() {
+        let err = interpolate("name = \"web\"\nimage = \"${MISSING}\"").unwrap_err();
+        assert_eq!(err.exit_code(), 6);
+        assert_eq!(
+            err.to_string(),
+            "Variable ${MISSING} used by `image` on line 2 is not set"
+        );
+
+        let err = interpolate("image = \"${IMAGE\"").unwrap_err();
+        assert_eq!(err.exit_code(), 8);
+        assert!(
+            err.to_string()
+                .starts_with("Unterminated variable on line 1")
+        );
+    }
+
+    #[test]
+    fn reads_env_files() {
+        let dir = TestDir::new(&[(
+            ".env",
+            "# comment\nDATABASE_URL=postgres://db\nGREETING=\"hello world\"\n\nEMPTY=\n",
+        )]);
+
+        let vars = PyriteTomlService::read_env_file(Path::new(&dir.path(".env"))).unwrap();
+
+        assert_eq!(vars.len(), 3);
+        assert_eq!(vars["DATABASE_URL"], "postgres://db");
+        assert_eq!(vars["GREETING"], "hello world");
+        assert_eq!(vars["EMPTY"], "");
+
+        assert!(PyriteTomlService::read_env_file(Path::new(&dir.path("missing.env"))).is_err());
+    }
+
+    fn table(raw: &str) -> Table {
+        toml::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn merges_values() {
+        let mut base = table(
+            r#"
+            plan = "small"
+            ports = [80]
+            env = { A = "1", B = "2" }
+            "#,
+        );
+
+        let overlay = table(
+            r#"
+            plan = "large"
+            ports = [443]
+            env = { B = "3", C = "4" }
+            "#,
+        );
+        for (key, value) in overlay.clone() {
+            PyriteTomlService::merge_value(&mut base, key, value, &[]);
+        }
+        assert_eq!(
+            base,
+            table(
+                r#"
+                plan = "large"
+                ports = [443]
+                env = { A = "1", B = "3", C = "4" }
+                "#
+            )
+        );
+
+        for (key, value) in overlay {
+            PyriteTomlService::merge_value(&mut base, key, value, &["ports".to_owned()]);
+        }
+        assert_eq!(
+            base["ports"],
+            toml::Value::Array(vec![443.into(), 443.into()])
+        );
+    }
+
+    #[test]
+    fn merges_overlay_services_by_name() {
+        let mut base = table(
+            r#"
+            [[services]]
+            name = "web"
+            plan = "small"
+            regions = ["eu"]
+            ports = [80]
+
+            [[services]]
+            name = "db"
+            type = "postgres"
+            "#,
+        );
+        let overlay = table(
+            r#"
+            [[services]]
+            name = "web"
+            plan = "large"
+            regions = ["us"]
+            ports = [443]
+            extend = ["ports"]
+
+            [[services]]
+            name = "worker"
+            image = "worker:latest"
+            "#,
+        );
+
+        PyriteTomlService::merge_overlay(&mut base, overlay).unwrap();
+        assert_eq!(
+            base,
+            table(
+                r#"
+                [[services]]
+                name = "web"
+                plan = "large"
+                regions = ["us"]
+                ports = [80, 443]
+
+                [[services]]
+                name = "db"
+                type = "postgres"
+
+                [[services]]
+                name = "worker"
+                image = "worker:latest"
+                "#
+            )
+        );
+
+        let overlay = table("[[services]]\nplan = \"large\"");
+        assert!(PyriteTomlService::merge_overlay(&mut base, overlay).is_err());
+    }
+
+    #[test]
+    fn renders_environment_defaults() {
+        let dir = TestDir::new(&[(
+            "pyrite.toml",
+            r#"
+            [[services]]
+            name = "web"
+
+            [[services]]
+            name = "db"
+            environment = "shared"
+
+            [environments.staging]
+            services = [{ name = "worker" }]
+            "#,
+        )]);
+        let file_path = dir.path("pyrite.toml");
+
+        let rendered = PyriteTomlService::render(&file_path, Some("staging"));
+
+        let environments = rendered.unwrap()["services"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|service| service["environment"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(environments, ["staging", "shared", "staging"]);
+    }
+
+    #[test]
+    fn only_interpolates_the_selected_overlay() {
+        let dir = TestDir::new(&[(
+            "pyrite.toml",
+            r#"
+            [[services]]
+            name = "web"
+            image = "web:${PYRITE_TEST_UNSET_TAG:-latest}"
+
+            [environments.staging]
+            services = [{ name = "web", image = "web:${PYRITE_TEST_UNSET_TAG}" }]
+
+            [[environments.preview.services]]
+            name = "web"
+            storage = ${PYRITE_TEST_UNSET_SIZE}
+            "#,
+        )]);
+        let file_path = dir.path("pyrite.toml");
+
+        let rendered = PyriteTomlService::render(&file_path, None);
+        let staging = PyriteTomlService::render(&file_path, Some("staging"));
+        let preview = PyriteTomlService::render(&file_path, Some("preview"));
+
+        assert_eq!(
+            rendered.unwrap()["services"][0]["image"].as_str(),
+            Some("web:latest")
+        );
+
+        let err = PyriteError::from(staging.unwrap_err());
+        assert_eq!(err.exit_code(), 6);
+        assert!(err.to_string().contains("${PYRITE_TEST_UNSET_TAG}"));
+
+        let err = PyriteError::from(preview.unwrap_err());
+        assert!(err.to_string().contains("${PYRITE_TEST_UNSET_SIZE}"));
+    }
+
+    fn load_diagnostic(files: &[(&str, &str)], env: Option<&str>) -> TomlDiagnostic {
+        let dir = TestDir::new(files);
+        let res = PyriteTomlService::load(&dir.path("pyrite.toml"), env);
+        *res.unwrap_err().downcast::<TomlDiagnostic>().unwrap()
+    }
+
+    #[test]
+    fn maps_spans_to_the_raw_source() {
+        let source = TomlSource {
+            path: "pyrite.toml".to_owned(),
+            raw: r#"a = "${A}" b"#.to_owned(),
+            interpolated: r#"a = "long value" b"#.to_owned(),
+            substitutions: vec![(5..15, 5..9)],
+        };
+
+        assert_eq!(source.get_raw_offset(3, false), 3);
+        assert_eq!(source.get_raw_offset(5, false), 5);
+        assert_eq!(source.get_raw_offset(8, false), 5);
+        assert_eq!(source.get_raw_offset(8, true), 9);
+        assert_eq!(source.get_raw_offset(17, false), 11);
+    }
+
+    #[test]
+    fn reports_errors_before_interpolation() {
+        let diagnostic = load_diagnostic(
+            &[(
+                "pyrite.toml",
+                "project_id = \"p\"\nservices = [{ name = \"${NAME:-a-long-service-name}\", storage = \"big\" }]\n",
+            )],
+            None,
+        );
+
+        let location = diagnostic.location.unwrap();
+        assert_eq!(location.line, 2);
+        assert_eq!(location.column, 64);
+        assert_eq!(
+            location.line_text,
+            "services = [{ name = \"${NAME:-a-long-service-name}\", storage = \"big\" }]"
+        );
+    }
+
+    #[test]
+    fn reports_errors_in_the_overlay_that_set_them() {
+        let base = "project_id = \"p\"\n\n[[services]]\nname = \"db\"\ntype = \"postgres\"\nplan = \"small\"\nversion = \"16\"\n";
+
+        let diagnostic = load_diagnostic(
+            &[
+                ("pyrite.toml", base),
+                (
+                    "pyrite.staging.toml",
+                    "[[services]]\nname = \"db\"\nstorage = \"big\"\n",
+                ),
+            ],
+            Some("staging"),
+        );
+        assert!(diagnostic.path.ends_with("pyrite.staging.toml"));
+        assert_eq!(diagnostic.location.unwrap().line, 3);
+
+        let base = format!(
+            "{}\n[environments.staging]\nservices = [{{ name = \"db\", storage = \"big\" }}]\n",
+            base
+        );
+        let diagnostic = load_diagnostic(&[("pyrite.toml", &base)], Some("staging"));
+        assert!(diagnostic.path.ends_with("pyrite.toml"));
+        let location = diagnostic.location.unwrap();
+        assert_eq!(location.line, 10);
+        assert_eq!(location.column, 38);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_on_load() {
+        let dir = TestDir::new(&[(
+            "pyrite.toml",
+            "project_id = \"p\"\n\n[[services]]\nname = \"web\"\nenvironment = \"prod\"\ntype = \"docker\"\nplan = \"small\"\nhealthchecks = []\n",
+        )]);
+        let file_path = dir.path("pyrite.toml");
+
+        let loaded = PyriteTomlService::load(&file_path, None);
+        let rendered = PyriteTomlService::render(&file_path, None);
+
+        let diagnostic = *loaded.unwrap_err().downcast::<TomlDiagnostic>().unwrap();
+        assert!(
+            diagnostic
+                .message
+                .starts_with("unknown field `healthchecks`, expected one of")
+        );
+        assert_eq!(
+            diagnostic.hint.as_deref(),
+            Some("did you mean `health_checks`?")
+        );
+        assert_eq!(diagnostic.location.unwrap().line, 8);
+        assert!(rendered.is_err());
+    }
+
+    #[test]
+    fn documents_overlays_in_the_schema() {
+        let schema = PyriteTomlService::get_json_schema();
+        let service = &schema["$defs"]["TomlService"];
+        assert_eq!(
+            service["required"],
+            serde_json::json!(["name", "type", "plan"])
+        );
+
+        let overlay_service = &schema["$defs"]["TomlOverlayService"];
+        assert_eq!(overlay_service["required"], serde_json::json!(["name"]));
+        assert_eq!(overlay_service["properties"]["extend"]["type"], "array");
+        assert_eq!(
+            schema["properties"]["environments"]["additionalProperties"]["properties"]["services"]
+                ["items"]["$ref"],
+            "#/$defs/TomlOverlayService"
+        );
+    }
+
+    #[test]
+    fn validates_keys_by_service_type() {
+        let dir = TestDir::new(&[(
+            "pyrite.toml",
+            r#"
+            project_id = "p"
+
+            [[services]]
+            name = "db"
+            environment = "prod"
+            type = "postgres"
+            plan = "small"
+            version = "16"
+            image = "postgres:16"
+            ports = [{ port = 5432, protocol = "tcp" }]
+
+            [[services]]
+            name = "cache"
+            environment = "prod"
+            type = "redis"
+            plan = "small"
+            "#,
+        )]);
+        let file_path = dir.path("pyrite.toml");
+
+        let issues = PyriteTomlService::validate(&file_path, None);
+
+        assert_eq!(
+            issues.unwrap(),
+            [
+                "services[0].image: not supported by postgres services",
+                "services[0].ports: not supported by postgres services",
+                "services[1]: type redis is not supported, expected docker or postgres",
+            ]
+        );
+    }
+}
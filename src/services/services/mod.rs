@@ -1,25 +1,21 @@
-use pyrite_client_rs::{
-    helpers::request::ReqWithMetadata,
-    pyrite::v1::services::v1::{
-        ServiceById, ServicesByTeamIdOrProjectId, UpsertServiceDto, UpsertServiceResponseDto,
-        common::v1::{Service, Services},
-        services_by_team_id_or_project_id::Id,
-        services_service_client::ServicesServiceClient,
-    },
+use pyrite_client_rs::pyrite::v1::services::v1::{
+    ServiceById, ServicesByTeamIdOrProjectId, UpsertServiceDto, UpsertServiceResponseDto,
+    common::v1::{Service, Services},
+    services_by_team_id_or_project_id::Id,
+    services_service_client::ServicesServiceClient,
 };
-use tonic::{Request, transport::channel::Channel};
 
-use crate::utils::PYRITE_API_BASE_URL;
+use crate::utils::error::PyriteError;
 
-use super::AuthService;
+use super::{AuthChannel, ChannelService};
 
 #[derive(Debug, Clone)]
 pub(crate) struct ServicesService;
 
 impl ServicesService {
     pub async fn get_services_client()
-    -> Result<ServicesServiceClient<Channel>, Box<dyn std::error::Error>> {
-        let client = ServicesServiceClient::connect(PYRITE_API_BASE_URL).await?;
+    -> Result<ServicesServiceClient<AuthChannel>, Box<dyn std::error::Error>> {
+        let client = ServicesServiceClient::new(ChannelService::get_channel_with_auth().await?);
         Ok(client)
     }
 
@@ -27,55 +23,52 @@ impl ServicesService {
         team_id: Option<String>,
         project_id: Option<String>,
     ) -> Result<Services, Box<dyn std::error::Error>> {
-        let mut client = Self::get_services_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-        let req: Request<ServicesByTeamIdOrProjectId> = ReqWithMetadata::with_metadata(
-            ServicesByTeamIdOrProjectId {
-                id: project_id.map(Id::ProjectId).or(team_id.map(Id::TeamId)),
+        let client = Self::get_services_client().await?;
+        let id = project_id.map(Id::ProjectId).or(team_id.map(Id::TeamId));
+
+        ChannelService::with_retry(|| {
+            let mut client = client.clone();
+            let req = ServicesByTeamIdOrProjectId {
+                id: id.to_owned(),
                 with_meta: None,
                 for_team_volume: None,
-            },
-            metadata,
-        );
-
-        client
-            .find_all_services(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+            };
+            async move { client.find_all_services(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 
     pub async fn get_service(service_id: String) -> Result<Service, Box<dyn std::error::Error>> {
-        let mut client = Self::get_services_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-        let req: Request<ServiceById> = ReqWithMetadata::with_metadata(
-            ServiceById {
-                id: service_id,
-                with_meta: Some(true),
-            },
-            metadata,
-        );
+        let client = Self::get_services_client().await?;
 
-        client
-            .find_one_service(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+        ChannelService::with_retry(|| {
+            let mut client = client.clone();
+            let req = ServiceById {
+                id: service_id.to_owned(),
+                with_meta: Some(true),
+            };
+            async move { client.find_one_service(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 
+    // An upsert starts a deployment, retrying one that reached the API could start a second
     pub async fn upsert_service(
         upsert_service_dto: UpsertServiceDto,
     ) -> Result<UpsertServiceResponseDto, Box<dyn std::error::Error>> {
-        let mut client = Self::get_services_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-
-        let req: Request<UpsertServiceDto> =
-            ReqWithMetadata::with_metadata(upsert_service_dto, metadata);
+        let client = Self::get_services_client().await?;
 
-        client
-            .upsert_service(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+        ChannelService::with_retry_unsent(|| {
+            let mut client = client.clone();
+            let req = upsert_service_dto.to_owned();
+            async move { client.upsert_service(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 }
@@ -1,48 +1,47 @@
-use pyrite_client_rs::{
-    helpers::request::ReqWithMetadata,
-    pyrite::v1::{
-        common::v1::Empty,
-        teams::v1::{Team, TeamById, Teams, team_service_client::TeamServiceClient},
-    },
+use pyrite_client_rs::pyrite::v1::{
+    common::v1::Empty,
+    teams::v1::{Team, TeamById, Teams, team_service_client::TeamServiceClient},
 };
-use tonic::{Request, transport::channel::Channel};
 
-use crate::utils::PYRITE_API_BASE_URL;
+use crate::utils::error::PyriteError;
 
-use super::AuthService;
+use super::{AuthChannel, ChannelService};
 
 #[derive(Debug, Clone)]
 pub(crate) struct TeamsService;
 
 impl TeamsService {
-    pub async fn get_teams_client() -> Result<TeamServiceClient<Channel>, Box<dyn std::error::Error>>
-    {
-        let client = TeamServiceClient::connect(PYRITE_API_BASE_URL).await?;
+    pub async fn get_teams_client()
+    -> Result<TeamServiceClient<AuthChannel>, Box<dyn std::error::Error>> {
+        let client = TeamServiceClient::new(ChannelService::get_channel_with_auth().await?);
         Ok(client)
     }
 
     pub async fn list_teams() -> Result<Teams, Box<dyn std::error::Error>> {
-        let mut client = Self::get_teams_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-        let req: Request<Empty> = ReqWithMetadata::with_metadata(Empty::default(), metadata);
-
-        client
-            .find_all_teams(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+        let client = Self::get_teams_client().await?;
+
+        ChannelService::with_retry(|| {
+            let mut client = client.clone();
+            let req = Empty::default();
+            async move { client.find_all_teams(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 
     pub async fn get_team(team_id: String) -> Result<Team, Box<dyn std::error::Error>> {
-        let mut client = Self::get_teams_client().await?;
-        let metadata = AuthService::get_metadata().await?;
-        let req: Request<TeamById> =
-            ReqWithMetadata::with_metadata(TeamById { id: team_id }, metadata);
-
-        client
-            .find_one_team(req)
-            .await
-            .map(|res| res.into_inner())
-            .map_err(|err| err.message().into())
+        let client = Self::get_teams_client().await?;
+
+        ChannelService::with_retry(|| {
+            let mut client = client.clone();
+            let req = TeamById {
+                id: team_id.to_owned(),
+            };
+            async move { client.find_one_team(req).await }
+        })
+        .await
+        .map(|res| res.into_inner())
+        .map_err(|err| PyriteError::from(err).into())
     }
 }
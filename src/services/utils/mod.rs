@@ -1,11 +1,48 @@
-use std::{error::Error, future::Future};
+use std::{collections::BTreeMap, error::Error, future::Future};
 
+use base64::{
+    Engine,
+    prelude::{BASE64_STANDARD, BASE64_STANDARD_NO_PAD},
+};
+
+use chrono::{DateTime, Utc};
 use cliclack::spinner;
+use console::style;
+use pyrite_client_rs::pyrite::v1::services::v1::common::v1::{
+    ServiceEnvironment, service_environment,
+};
+use serde_json::Value;
+
+use super::AuthService;
 
 #[derive(Debug, Clone)]
 pub(crate) struct UtilsService;
 
 impl UtilsService {
+    // e.g. "in 5 minutes" or "3 hours ago"
+    pub fn get_relative_time(time: DateTime<Utc>) -> String {
+        let seconds = (time - Utc::now()).num_seconds();
+
+        let abs_seconds = seconds.unsigned_abs();
+        let (value, unit) = match abs_seconds {
+            0..60 => (abs_seconds, "second"),
+            60..3600 => (abs_seconds / 60, "minute"),
+            3600..86400 => (abs_seconds / 3600, "hour"),
+            _ => (abs_seconds / 86400, "day"),
+        };
+        let unit = if value == 1 {
+            unit.to_owned()
+        } else {
+            format!("{}s", unit)
+        };
+
+        if seconds >= 0 {
+            format!("in {} {}", value, unit)
+        } else {
+            format!("{} {} ago", value, unit)
+        }
+    }
+
     pub async fn with_progress<T, R>(
         fun: impl FnOnce() -> T,
         msg: &str,
@@ -15,18 +52,20 @@ impl UtilsService {
     where
         T: Future<Output = Result<R, Box<dyn Error>>>,
     {
+        let profile = style(format!("(profile: {})", AuthService::get_profile())).dim();
+
         let progress = spinner();
-        progress.start(msg);
+        progress.start(format!("{} {}", msg, profile));
 
         let result = fun().await;
 
         match result {
             Ok(x) => {
-                progress.stop(success);
+                progress.stop(format!("{} {}", success, profile));
                 Ok(x)
             }
             Err(err) => {
-                progress.error(failed);
+                progress.error(format!("{} {}", failed, profile));
                 Err(err)
             }
         }
@@ -135,4 +174,89 @@ impl UtilsService {
             _ => comfy_table::Color::Yellow,
         }
     }
+
+    pub fn get_active_deployment_status(service_environment: &ServiceEnvironment) -> Option<i32> {
+        service_environment
+            .active_deployment
+            .as_ref()
+            .map(|active_deployment| match active_deployment {
+                service_environment::ActiveDeployment::DockerDeployment(deployment) => {
+                    deployment.status
+                }
+                service_environment::ActiveDeployment::PostgresDeployment(deployment) => {
+                    deployment.status
+                }
+            })
+    }
+
+    pub fn get_active_deployment_id(service_environment: &ServiceEnvironment) -> Option<&str> {
+        service_environment
+            .active_deployment
+            .as_ref()
+            .map(|active_deployment| match active_deployment {
+                service_environment::ActiveDeployment::DockerDeployment(deployment) => {
+                    deployment.id.as_str()
+                }
+                service_environment::ActiveDeployment::PostgresDeployment(deployment) => {
+                    deployment.id.as_str()
+                }
+            })
+    }
+
+    pub fn is_deployment_status_final(deployment_status: i32) -> bool {
+        match deployment_status {
+            // Build Succeeded is followed by a deploy
+            3011 => false,
+            3000..=4999 => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_deployment_status_failed(deployment_status: i32) -> bool {
+        (4000..=4999).contains(&deployment_status)
+    }
+
+    // Oneof fields are serialized inline, e.g. `dockerConfig` or `dockerDeployment`
+    pub fn get_oneof_value(value: Value, suffix: &str) -> Value {
+        match value {
+            Value::Object(map) => map
+                .into_iter()
+                .find(|(key, value)| key.ends_with(suffix) && value.is_object())
+                .map(|(_, value)| value)
+                .unwrap_or_default(),
+            _ => Value::Null,
+        }
+    }
+
+    // Env is sent as base64 encoded JSON
+    pub fn decode_env(encoded: Option<&str>) -> Result<BTreeMap<String, Value>, Box<dyn Error>> {
+        let Some(encoded) = encoded.filter(|encoded| !encoded.is_empty()) else {
+            return Ok(BTreeMap::new());
+        };
+
+        let decoded = BASE64_STANDARD_NO_PAD
+            .decode(encoded)
+            .or_else(|_| BASE64_STANDARD.decode(encoded))
+            .unwrap_or_else(|_| encoded.as_bytes().to_vec());
+
+        serde_json::from_slice(&decoded)
+            .map_err(|err| format!("Failed to decode the env of the deployment: {}", err).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_env() {
+        let encoded = BASE64_STANDARD.encode(r#"{"A":"1"}"#);
+        assert_eq!(
+            UtilsService::decode_env(Some(&encoded)).unwrap(),
+            BTreeMap::from([("A".to_owned(), Value::from("1"))])
+        );
+        assert!(UtilsService::decode_env(None).unwrap().is_empty());
+        assert!(UtilsService::decode_env(Some("")).unwrap().is_empty());
+        assert!(UtilsService::decode_env(Some("not json")).is_err());
+    }
 }
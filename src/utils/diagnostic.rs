@@ -0,0 +1,197 @@
+use std::{
+    fmt::{Debug, Display},
+    ops::Range,
+};
+
+// A parse error pointing at the offending span of a source file
+pub(crate) struct TomlDiagnostic {
+    pub message: String,
+    pub path: String,
+    pub location: Option<SourceLocation>,
+    pub hint: Option<String>,
+}
+
+pub(crate) struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+    pub line_text: String,
+    pub width: usize,
+}
+
+impl TomlDiagnostic {
+    pub fn new(path: &str, source: &str, err: &toml::de::Error) -> Self {
+        Self::with_span(path, source, err.message(), err.span())
+    }
+
+    pub fn with_span(path: &str, source: &str, message: &str, span: Option<Range<usize>>) -> Self {
+        let message = message.trim().to_owned();
+
+        Self {
+            hint: get_unknown_name_hint(&message),
+            message,
+            path: path.to_owned(),
+            location: span.map(|span| SourceLocation::new(source, span)),
+        }
+    }
+}
+
+impl SourceLocation {
+    fn new(source: &str, span: Range<usize>) -> Self {
+        let start = span.start.min(source.len());
+
+        let line_start = source[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |idx| start + idx);
+
+        Self {
+            line: source[..start].matches('\n').count() + 1,
+            column: source[line_start..start].chars().count() + 1,
+            line_text: source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+            // Multi-line spans are only underlined on their first line
+            width: source[start..span.end.clamp(start, line_end)]
+                .chars()
+                .count()
+                .max(1),
+        }
+    }
+}
+
+impl Display for TomlDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(location) = &self.location else {
+            write!(f, "{}\n --> {}", self.message, self.path)?;
+            if let Some(hint) = &self.hint {
+                write!(f, "\n  = help: {}", hint)?;
+            }
+            return Ok(());
+        };
+
+        let gutter = " ".repeat(location.line.to_string().len());
+
+        writeln!(f, "{}", self.message)?;
+        writeln!(
+            f,
+            "{}--> {}:{}:{}",
+            gutter, self.path, location.line, location.column
+        )?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", location.line, location.line_text)?;
+        write!(
+            f,
+            "{} | {}{}",
+            gutter,
+            " ".repeat(location.column - 1),
+            "^".repeat(location.width)
+        )?;
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{} = help: {}", gutter, hint)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Keep the diagnostic readable when printed with `Debug`
+impl Debug for TomlDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for TomlDiagnostic {}
+
+// serde reports "unknown field `x`, expected one of `a`, `b`" for misspelled keys
+fn get_unknown_name_hint(message: &str) -> Option<String> {
+    if !message.starts_with("unknown field") && !message.starts_with("unknown variant") {
+        return None;
+    }
+
+    let mut names = message.split('`').skip(1).step_by(2);
+    let unknown = names.next()?;
+    let candidates = names.collect::<Vec<_>>();
+
+    did_you_mean(unknown, &candidates).map(|name| format!("did you mean `{}`?", name))
+}
+
+pub(crate) fn did_you_mean<'a>(unknown: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (unknown.chars().count() / 3).max(2);
+
+    candidates
+        .iter()
+        .map(|candidate| (*candidate, levenshtein(unknown, candidate)))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(candidate, _)| candidate)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_edit_distance() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("image", "image"), 0);
+        assert_eq!(levenshtein("imgae", "image"), 2);
+        assert_eq!(levenshtein("port", "ports"), 1);
+        assert_eq!(levenshtein("", "plan"), 4);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn suggests_close_names() {
+        let candidates = ["image", "plan", "ports", "health_checks"];
+
+        assert_eq!(did_you_mean("imgae", &candidates), Some("image"));
+        assert_eq!(did_you_mean("port", &candidates), Some("ports"));
+        assert_eq!(
+            did_you_mean("healthchecks", &candidates),
+            Some("health_checks")
+        );
+        assert_eq!(did_you_mean("runtime", &candidates), None);
+        assert_eq!(did_you_mean("plan", &[]), None);
+    }
+
+    #[test]
+    fn hints_unknown_fields() {
+        assert_eq!(
+            get_unknown_name_hint("unknown field `imgae`, expected one of `image`, `plan`"),
+            Some("did you mean `image`?".to_owned())
+        );
+        assert_eq!(get_unknown_name_hint("missing field `plan`"), None);
+    }
+
+    #[test]
+    fn underlines_the_span() {
+        let diagnostic = TomlDiagnostic::with_span(
+            "pyrite.toml",
+            "name = \"web\"\nplan = 5\n",
+            "invalid type",
+            Some(20..21),
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "invalid type\n --> pyrite.toml:2:8\n  |\n2 | plan = 5\n  |        ^"
+        );
+    }
+}
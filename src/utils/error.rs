@@ -0,0 +1,249 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+};
+
+use tonic::{Code, Status};
+
+use super::diagnostic::TomlDiagnostic;
+
+// Shown in `pyrite --help`, keep in sync with `PyriteError::exit_code`
+pub(crate) const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  1  Unexpected error
+  2  `deploy --plan` found changes
+  3  Not logged in, or the session expired
+  4  Permission denied
+  5  Not found
+  6  Invalid input or arguments
+  7  Network error
+  8  Invalid config file
+  9  A deployment failed";
+
+// Errors the user or a script can act on, each with its own exit code
+pub(crate) enum PyriteError {
+    Auth(String),
+    PermissionDenied(String),
+    NotFound(String),
+    Validation(String),
+    Network(String),
+    ConfigParse(String),
+    // `deploy --plan` found changes, the plan itself is already printed
+    ChangesPending(String),
+    DeploymentFailed(String),
+    Other(String),
+}
+
+impl PyriteError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PyriteError::Other(_) => 1,
+            PyriteError::ChangesPending(_) => 2,
+            PyriteError::Auth(_) => 3,
+            PyriteError::PermissionDenied(_) => 4,
+            PyriteError::NotFound(_) => 5,
+            PyriteError::Validation(_) => 6,
+            PyriteError::Network(_) => 7,
+            PyriteError::ConfigParse(_) => 8,
+            PyriteError::DeploymentFailed(_) => 9,
+        }
+    }
+
+    // Adds context to the message, keeping the exit code
+    pub fn map_message(self, fun: impl FnOnce(String) -> String) -> Self {
+        match self {
+            PyriteError::Auth(message) => PyriteError::Auth(fun(message)),
+            PyriteError::PermissionDenied(message) => PyriteError::PermissionDenied(fun(message)),
+            PyriteError::NotFound(message) => PyriteError::NotFound(fun(message)),
+            PyriteError::Validation(message) => PyriteError::Validation(fun(message)),
+            PyriteError::Network(message) => PyriteError::Network(fun(message)),
+            PyriteError::ConfigParse(message) => PyriteError::ConfigParse(fun(message)),
+            PyriteError::ChangesPending(message) => PyriteError::ChangesPending(fun(message)),
+            PyriteError::DeploymentFailed(message) => PyriteError::DeploymentFailed(fun(message)),
+            PyriteError::Other(message) => PyriteError::Other(fun(message)),
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            PyriteError::Auth(_) => {
+                Some("Run `pyrite login` to log in, or set PYRITE_TOKEN for non-interactive use")
+            }
+            PyriteError::PermissionDenied(_) => {
+                Some("Check that the active profile is a member of the team, see `pyrite whoami`")
+            }
+            PyriteError::NotFound(_) => {
+                Some("Check the name or id, the `list` commands show the available ones")
+            }
+            PyriteError::Validation(_) => Some("Check the arguments, see `pyrite --help`"),
+            PyriteError::Network(_) => {
+                Some("Check your connection, or point --api-url at a reachable endpoint and retry")
+            }
+            PyriteError::ConfigParse(_) => {
+                Some("Run `pyrite config schema` to see the supported keys")
+            }
+            PyriteError::DeploymentFailed(_) => {
+                Some("Check the deployment with `pyrite environments get`")
+            }
+            PyriteError::ChangesPending(_) | PyriteError::Other(_) => None,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            PyriteError::Auth(message)
+            | PyriteError::PermissionDenied(message)
+            | PyriteError::NotFound(message)
+            | PyriteError::Validation(message)
+            | PyriteError::Network(message)
+            | PyriteError::ConfigParse(message)
+            | PyriteError::ChangesPending(message)
+            | PyriteError::DeploymentFailed(message)
+            | PyriteError::Other(message) => message,
+        }
+    }
+}
+
+impl Display for PyriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl Debug for PyriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for PyriteError {}
+
+// Keeps the gRPC code that `Status::message` alone would drop
+impl From<Status> for PyriteError {
+    fn from(status: Status) -> Self {
+        let message = match status.message() {
+            "" => format!("The API returned {:?}", status.code()),
+            message => message.to_owned(),
+        };
+
+        match status.code() {
+            Code::Unauthenticated => PyriteError::Auth(message),
+            Code::PermissionDenied => PyriteError::PermissionDenied(message),
+            Code::NotFound => PyriteError::NotFound(message),
+            Code::InvalidArgument
+            | Code::FailedPrecondition
+            | Code::OutOfRange
+            | Code::AlreadyExists => PyriteError::Validation(message),
+            Code::Unavailable | Code::DeadlineExceeded => PyriteError::Network(message),
+            _ => PyriteError::Other(message),
+        }
+    }
+}
+
+// Usage errors are invalid input, the `error:` prefix is added again by `main`
+impl From<clap::Error> for PyriteError {
+    fn from(err: clap::Error) -> Self {
+        let message = err.to_string();
+        let message = message.strip_prefix("error: ").unwrap_or(&message);
+        PyriteError::Validation(message.trim_end().to_owned())
+    }
+}
+
+// Classifies errors that were propagated with `?` from other crates
+impl From<Box<dyn Error>> for PyriteError {
+    fn from(err: Box<dyn Error>) -> Self {
+        let err = match err.downcast::<PyriteError>() {
+            Ok(err) => return *err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<Status>() {
+            Ok(status) => return (*status).into(),
+            Err(err) => err,
+        };
+
+        if let Some(diagnostic) = err.downcast_ref::<TomlDiagnostic>() {
+            return PyriteError::ConfigParse(diagnostic.to_string());
+        }
+
+        if let Some(err) = err.downcast_ref::<reqwest::Error>()
+            && (err.is_connect() || err.is_timeout())
+        {
+            return PyriteError::Network(err.to_string());
+        }
+
+        if err.is::<tonic::transport::Error>() {
+            return PyriteError::Network(err.to_string());
+        }
+
+        PyriteError::Other(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_status_codes() {
+        let cases = [
+            (Code::Unauthenticated, 3),
+            (Code::PermissionDenied, 4),
+            (Code::NotFound, 5),
+            (Code::InvalidArgument, 6),
+            (Code::FailedPrecondition, 6),
+            (Code::OutOfRange, 6),
+            (Code::AlreadyExists, 6),
+            (Code::Unavailable, 7),
+            (Code::DeadlineExceeded, 7),
+            (Code::Internal, 1),
+            (Code::Unknown, 1),
+        ];
+
+        for (code, exit_code) in cases {
+            let err = PyriteError::from(Status::new(code, "message"));
+            assert_eq!(err.exit_code(), exit_code, "{:?}", code);
+            assert_eq!(err.to_string(), "message");
+        }
+    }
+
+    #[test]
+    fn keeps_the_code_of_empty_statuses() {
+        let err = PyriteError::from(Status::new(Code::NotFound, ""));
+        assert_eq!(err.to_string(), "The API returned NotFound");
+    }
+
+    #[test]
+    fn classifies_boxed_errors() {
+        let err: Box<dyn Error> = PyriteError::Validation("invalid".to_owned()).into();
+        assert_eq!(PyriteError::from(err).exit_code(), 6);
+
+        let err: Box<dyn Error> = Status::permission_denied("denied").into();
+        assert_eq!(PyriteError::from(err).exit_code(), 4);
+
+        let err: Box<dyn Error> = "something else".into();
+        let err = PyriteError::from(err);
+        assert_eq!(err.exit_code(), 1);
+        assert_eq!(err.to_string(), "something else");
+    }
+
+    #[test]
+    fn maps_usage_errors_to_validation() {
+        let err = clap::Command::new("pyrite")
+            .try_get_matches_from(["pyrite", "--bogus"])
+            .unwrap_err();
+        let err = PyriteError::from(err);
+        assert_eq!(err.exit_code(), 6);
+        assert!(
+            err.to_string()
+                .starts_with("unexpected argument '--bogus' found")
+        );
+    }
+
+    #[test]
+    fn maps_messages_and_keeps_the_exit_code() {
+        let err = PyriteError::NotFound("pyrite.toml".to_owned())
+            .map_message(|message| format!("Failed to read {}", message));
+        assert_eq!(err.exit_code(), 5);
+        assert_eq!(err.to_string(), "Failed to read pyrite.toml");
+    }
+}
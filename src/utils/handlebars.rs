@@ -1,8 +1,126 @@
-use handlebars::handlebars_helper;
+use chrono::{
+    DateTime, Local,
+    format::{Item, StrftimeItems},
+};
+use handlebars::{RenderError, RenderErrorReason, handlebars_helper};
 use serde_json::Value;
 
+use crate::services::UtilsService;
+
+use super::TABLE_DATE_FORMAT;
+
 handlebars_helper!(is_equal_helper: |v1: Value,v2: Value| v1==v2);
 
+// e.g. {{date createdAt}} or {{date createdAt format="%Y-%m-%d"}}
+handlebars_helper!(date_helper: |value: str, {format: str = ""}| format_date(value, format)?);
+
+handlebars_helper!(time_ago_helper: |value: str| {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| UtilsService::get_relative_time(date.to_utc()))
+        .unwrap_or_else(|_| value.to_owned())
+});
+
+handlebars_helper!(service_status_helper: |status: Value| {
+    get_status(&status).map_or(status.to_string(), UtilsService::get_service_status_label)
+});
+
+handlebars_helper!(deployment_status_helper: |status: Value| {
+    get_status(&status).map_or(status.to_string(), UtilsService::get_deployment_status_label)
+});
+
+// chrono panics when writing an invalid specifier, so the format is checked first
+fn format_date(value: &str, format: &str) -> Result<String, RenderError> {
+    let format = if format.is_empty() {
+        TABLE_DATE_FORMAT
+    } else {
+        format
+    };
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(RenderErrorReason::Other(format!("Invalid date format: {}", format)).into());
+    }
+
+    Ok(DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Local).format(format).to_string())
+        .unwrap_or_else(|_| value.to_owned()))
+}
+
+// Statuses are numbers, but accept them as strings too
+fn get_status(status: &Value) -> Option<i32> {
+    match status {
+        Value::Number(number) => number.as_i64().and_then(|number| number.try_into().ok()),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
 pub(crate) fn setup_handlebars(handlebars: &mut handlebars::Handlebars) {
     handlebars.register_helper("isEqual", Box::new(is_equal_helper));
+    handlebars.register_helper("date", Box::new(date_helper));
+    handlebars.register_helper("timeAgo", Box::new(time_ago_helper));
+    handlebars.register_helper("serviceStatus", Box::new(service_status_helper));
+    handlebars.register_helper("deploymentStatus", Box::new(deployment_status_helper));
+}
+
+#[cfg(test)]
+mod tests {
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(template: &str, data: Value) -> String {
+        let mut handlebars = Handlebars::new();
+        setup_handlebars(&mut handlebars);
+        handlebars.render_template(template, &data).unwrap()
+    }
+
+    #[test]
+    fn compares_values() {
+        let data = json!({"a": 1, "b": 1, "c": "1"});
+        assert_eq!(render("{{isEqual a b}}", data.clone()), "true");
+        assert_eq!(render("{{isEqual a c}}", data), "false");
+    }
+
+    #[test]
+    fn formats_dates() {
+        let data = json!({"createdAt": "2024-06-15T12:00:00Z", "name": "web"});
+        assert_eq!(
+            render("{{date createdAt format=\"%Y\"}}", data.clone()),
+            "2024"
+        );
+        assert!(render("{{timeAgo createdAt}}", data.clone()).ends_with(" days ago"));
+        // Values that aren't dates are printed as is
+        assert_eq!(render("{{date name}} {{timeAgo name}}", data), "web web");
+    }
+
+    #[test]
+    fn rejects_invalid_date_formats() {
+        let mut handlebars = Handlebars::new();
+        setup_handlebars(&mut handlebars);
+        let data = json!({"createdAt": "2024-06-15T12:00:00Z"});
+        let err = handlebars
+            .render_template("{{date createdAt format=\"%Q\"}}", &data)
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid date format: %Q"));
+    }
+
+    #[test]
+    fn labels_statuses() {
+        let data = json!({"service": 3001, "deployment": "2001", "unknown": true});
+        assert_eq!(render("{{serviceStatus service}}", data.clone()), "Ready");
+        assert_eq!(
+            render("{{deploymentStatus deployment}}", data.clone()),
+            "Pending"
+        );
+        assert_eq!(render("{{serviceStatus unknown}}", data), "true");
+    }
+
+    #[test]
+    fn reads_statuses() {
+        assert_eq!(get_status(&json!(3001)), Some(3001));
+        assert_eq!(get_status(&json!("3001")), Some(3001));
+        assert_eq!(get_status(&json!(i64::MAX)), None);
+        assert_eq!(get_status(&json!("ready")), None);
+        assert_eq!(get_status(&Value::Null), None);
+    }
 }
@@ -1,18 +1,32 @@
 use cliclack::{Theme, ThemeState};
-use console::Style;
+use console::{Style, style};
+pub(crate) mod diagnostic;
+pub(crate) mod error;
 pub(crate) mod handlebars;
+pub(crate) mod schema;
 
 pub(crate) const PYRITE_API_BASE_URL: &str = "https://api-grpc.pyrite.cloud";
 pub(crate) const WORKFLOWS_BASE_URL: &str = "https://pyritecloud.github.io/workflows";
-pub(crate) const ERR_MSG: &str = "There was an error, please try again";
 pub(crate) const DOCKER_FILE: &str = "Dockerfile";
 
 pub(crate) const TABLE_DATE_FORMAT: &str = "%d-%m-%Y %I:%M:%S %p %:z";
 
-pub(crate) struct PyriteTheme;
+pub(crate) struct PyriteTheme {
+    // Active auth profile, shown after every outro
+    pub profile: String,
+}
 
 impl Theme for PyriteTheme {
     fn state_symbol_color(&self, _state: &ThemeState) -> Style {
         Style::new().color256(123)
     }
+
+    fn format_outro(&self, message: &str) -> String {
+        let color = self.bar_color(&ThemeState::Submit);
+        format!(
+            "{bar_end}  {message} {profile}\n",
+            bar_end = color.apply_to("└"),
+            profile = style(format!("(profile: {})", self.profile)).dim()
+        )
+    }
 }
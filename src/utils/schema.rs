@@ -0,0 +1,275 @@
+use std::fmt::Display;
+
+use pyrite_client_rs::pyrite::v1::services::v1::deployments::v1::{
+    DeploymentFileDto, DeploymentHealthCheckDto, DeploymentPortDto, DeploymentRegionDto,
+    DeploymentVolumeDto,
+};
+use schemars::{Schema, SchemaGenerator, json_schema};
+use serde::{
+    Deserializer,
+    de::{DeserializeOwned, Visitor},
+    forward_to_deserialize_any,
+};
+use serde_json::json;
+
+// The API types do not implement `JsonSchema`, so their field names are read from
+// the `Deserialize` impl which hands them to `deserialize_struct`. pbjson also lists the
+// camelCase JSON name of each field, only the snake_case proto names are kept.
+pub(crate) fn get_struct_fields<T: DeserializeOwned>() -> Vec<&'static str> {
+    let fields = match T::deserialize(FieldsDeserializer) {
+        Err(FieldsError(Some(fields))) => fields,
+        _ => &[],
+    };
+
+    fields
+        .iter()
+        .copied()
+        .filter(|field| !field.contains(|ch: char| ch.is_ascii_uppercase()))
+        .collect()
+}
+
+// `[environments.<env>]` overlays are merged by `PyriteTomlService::render` before the file is
+// deserialized, so they are only added to the schema. Overlay services only need a name to be
+// matched, and services without an environment get the one selected with `--env`.
+pub(crate) fn add_environments_schema(schema: &mut Schema) {
+    let Some(service) = schema
+        .pointer_mut("/$defs/TomlService")
+        .and_then(serde_json::Value::as_object_mut)
+    else {
+        return;
+    };
+
+    if let Some(required) = service
+        .get_mut("required")
+        .and_then(serde_json::Value::as_array_mut)
+    {
+        required.retain(|key| key != "environment");
+    }
+
+    let mut overlay_service = service.clone();
+    overlay_service.insert("required".to_owned(), json!(["name"]));
+    if let Some(properties) = overlay_service
+        .get_mut("properties")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        properties.insert(
+            "extend".to_owned(),
+            json!({
+                "description": "List keys whose items are appended to the base service instead of replacing its list",
+                "type": "array",
+                "items": { "type": "string" },
+            }),
+        );
+    }
+
+    if let Some(defs) = schema
+        .get_mut("$defs")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        defs.insert(
+            "TomlOverlayService".to_owned(),
+            serde_json::Value::Object(overlay_service),
+        );
+    }
+
+    if let Some(properties) = schema
+        .get_mut("properties")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        properties.insert(
+            "environments".to_owned(),
+            json!({
+                "description": "Overlays merged over this file with `--env <name>`",
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "properties": {
+                        "project_id": { "type": "string" },
+                        "services": {
+                            "type": "array",
+                            "items": { "$ref": "#/$defs/TomlOverlayService" },
+                        },
+                    },
+                    "additionalProperties": false,
+                },
+            }),
+        );
+    }
+}
+
+// JSON schema type of each field of an API type, as (field, type, required)
+type ProtoFields = &'static [(&'static str, &'static str, bool)];
+
+const REGION_FIELDS: ProtoFields = &[("region", "string", true), ("replicas", "integer", false)];
+const PORT_FIELDS: ProtoFields = &[
+    ("port", "integer", true),
+    ("protocol", "string", true),
+    ("is_public", "boolean", false),
+];
+const HEALTH_CHECK_FIELDS: ProtoFields = &[
+    ("port", "integer", true),
+    ("path", "string", false),
+    ("protocol", "string", true),
+];
+const VOLUME_FIELDS: ProtoFields = &[
+    ("volume_id", "string", true),
+    ("mount_path", "string", true),
+];
+const FILE_FIELDS: ProtoFields = &[("path", "string", true), ("content", "string", true)];
+
+pub(crate) fn region_list_schema(_generator: &mut SchemaGenerator) -> Schema {
+    proto_list_schema::<DeploymentRegionDto>(REGION_FIELDS)
+}
+
+pub(crate) fn port_list_schema(_generator: &mut SchemaGenerator) -> Schema {
+    proto_list_schema::<DeploymentPortDto>(PORT_FIELDS)
+}
+
+pub(crate) fn health_check_list_schema(_generator: &mut SchemaGenerator) -> Schema {
+    proto_list_schema::<DeploymentHealthCheckDto>(HEALTH_CHECK_FIELDS)
+}
+
+pub(crate) fn volume_list_schema(_generator: &mut SchemaGenerator) -> Schema {
+    proto_list_schema::<DeploymentVolumeDto>(VOLUME_FIELDS)
+}
+
+pub(crate) fn file_list_schema(_generator: &mut SchemaGenerator) -> Schema {
+    proto_list_schema::<DeploymentFileDto>(FILE_FIELDS)
+}
+
+// The properties are the fields of `T`, so a field added to the API is accepted even before
+// `fields` gives it a type
+fn proto_schema<T: DeserializeOwned>(fields: ProtoFields) -> Schema {
+    let struct_fields = get_struct_fields::<T>();
+
+    let properties = struct_fields
+        .iter()
+        .map(|field| {
+            let schema = match fields.iter().find(|(name, _, _)| name == field) {
+                Some((_, field_type, _)) => json!({ "type": field_type }),
+                None => json!({}),
+            };
+            (field.to_string(), schema)
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    let required = fields
+        .iter()
+        .filter(|(name, _, required)| *required && struct_fields.contains(name))
+        .map(|(name, _, _)| *name)
+        .collect::<Vec<_>>();
+
+    json_schema!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn proto_list_schema<T: DeserializeOwned>(fields: ProtoFields) -> Schema {
+    let items = proto_schema::<T>(fields);
+
+    json_schema!({
+        "type": ["array", "null"],
+        "items": items,
+    })
+}
+
+struct FieldsDeserializer;
+
+#[derive(Debug)]
+struct FieldsError(Option<&'static [&'static str]>);
+
+impl Display for FieldsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not a struct")
+    }
+}
+
+impl std::error::Error for FieldsError {}
+
+impl serde::de::Error for FieldsError {
+    fn custom<T: Display>(_msg: T) -> Self {
+        FieldsError(None)
+    }
+}
+
+impl<'de> Deserializer<'de> for FieldsDeserializer {
+    type Error = FieldsError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(FieldsError(None))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(FieldsError(Some(fields)))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_all_typed<T: DeserializeOwned>(fields: ProtoFields) {
+        let mut struct_fields = get_struct_fields::<T>();
+        struct_fields.sort();
+
+        let mut typed_fields = fields.iter().map(|(name, _, _)| *name).collect::<Vec<_>>();
+        typed_fields.sort();
+
+        assert_eq!(struct_fields, typed_fields);
+    }
+
+    #[test]
+    fn types_every_api_field() {
+        assert_all_typed::<DeploymentRegionDto>(REGION_FIELDS);
+        assert_all_typed::<DeploymentPortDto>(PORT_FIELDS);
+        assert_all_typed::<DeploymentHealthCheckDto>(HEALTH_CHECK_FIELDS);
+        assert_all_typed::<DeploymentVolumeDto>(VOLUME_FIELDS);
+        assert_all_typed::<DeploymentFileDto>(FILE_FIELDS);
+    }
+
+    // Deserializes like pbjson, which accepts the proto and the JSON name of each field
+    struct PbjsonPort;
+
+    impl<'de> serde::Deserialize<'de> for PbjsonPort {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer
+                .deserialize_struct(
+                    "PbjsonPort",
+                    &["port", "is_public", "isPublic"],
+                    serde::de::IgnoredAny,
+                )
+                .map(|_| PbjsonPort)
+        }
+    }
+
+    #[test]
+    fn skips_json_field_names() {
+        assert_eq!(get_struct_fields::<PbjsonPort>(), ["port", "is_public"]);
+    }
+
+    #[test]
+    fn builds_typed_schemas() {
+        let schema = proto_schema::<DeploymentPortDto>(PORT_FIELDS).to_value();
+        assert_eq!(schema["properties"]["port"], json!({ "type": "integer" }));
+        assert_eq!(
+            schema["properties"]["is_public"],
+            json!({ "type": "boolean" })
+        );
+        assert_eq!(schema["required"], json!(["port", "protocol"]));
+        assert_eq!(schema["additionalProperties"], json!(false));
+    }
+}